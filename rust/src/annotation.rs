@@ -0,0 +1,166 @@
+use crate::types::square_name;
+
+/// Colours for arrows and marked squares, named as in PGN comments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Brush {
+    Green,
+    Red,
+    Yellow,
+    Blue,
+}
+
+impl Brush {
+    pub fn letter(self) -> char {
+        match self {
+            Brush::Green => 'G',
+            Brush::Red => 'R',
+            Brush::Yellow => 'Y',
+            Brush::Blue => 'B',
+        }
+    }
+
+    /// Takes a letter like "G" or a name like "green".
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "g" | "green" => Some(Brush::Green),
+            "r" | "red" => Some(Brush::Red),
+            "y" | "yellow" => Some(Brush::Yellow),
+            "b" | "blue" => Some(Brush::Blue),
+            _ => None,
+        }
+    }
+}
+
+/// Arrows and marked squares drawn on the board for teaching or analysis.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Annotations {
+    pub squares: Vec<(usize, Brush)>,
+    pub arrows: Vec<(usize, usize, Brush)>,
+}
+
+impl Annotations {
+    pub fn is_empty(&self) -> bool {
+        self.squares.is_empty() && self.arrows.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.squares.clear();
+        self.arrows.clear();
+    }
+
+    /// Marks `square`, or unmarks it if it already has the same mark. A mark in another colour
+    /// is replaced.
+    pub fn toggle_square(&mut self, square: usize, brush: Brush) {
+        let old = self.squares.iter().position(|(s, _)| *s == square);
+        if let Some(i) = old {
+            if self.squares.remove(i).1 == brush {
+                return;
+            }
+        }
+        self.squares.push((square, brush));
+    }
+
+    /// Like `toggle_square`, for the arrow from `from` to `to`. An arrow needs two different
+    /// squares, so nothing happens if they're the same.
+    pub fn toggle_arrow(&mut self, from: usize, to: usize, brush: Brush) {
+        if from == to {
+            return;
+        }
+        let old = self
+            .arrows
+            .iter()
+            .position(|(f, t, _)| *f == from && *t == to);
+        if let Some(i) = old {
+            if self.arrows.remove(i).2 == brush {
+                return;
+            }
+        }
+        self.arrows.push((from, to, brush));
+    }
+
+    /// Formats the annotations as PGN comment commands, like `[%csl Gd4][%cal Ge2e4,Rg1f3]`.
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        if !self.squares.is_empty() {
+            let squares = self
+                .squares
+                .iter()
+                .map(|(square, brush)| format!("{}{}", brush.letter(), square_name(*square)))
+                .collect::<Vec<_>>();
+            pgn += &format!("[%csl {}]", squares.join(","));
+        }
+        if !self.arrows.is_empty() {
+            let arrows = self
+                .arrows
+                .iter()
+                .map(|(from, to, brush)| {
+                    format!(
+                        "{}{}{}",
+                        brush.letter(),
+                        square_name(*from),
+                        square_name(*to)
+                    )
+                })
+                .collect::<Vec<_>>();
+            pgn += &format!("[%cal {}]", arrows.join(","));
+        }
+        pgn
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::parse_square;
+
+    fn square(name: &str) -> usize {
+        parse_square(name).unwrap()
+    }
+
+    #[test]
+    fn parses_brushes_by_letter_or_name() {
+        assert_eq!(Brush::parse("G"), Some(Brush::Green));
+        assert_eq!(Brush::parse("red"), Some(Brush::Red));
+        assert_eq!(Brush::parse("Yellow"), Some(Brush::Yellow));
+        assert_eq!(Brush::parse("b"), Some(Brush::Blue));
+        assert_eq!(Brush::parse("purple"), None);
+    }
+
+    #[test]
+    fn exports_squares_and_arrows_as_pgn() {
+        let mut annotations = Annotations::default();
+        assert_eq!(annotations.to_pgn(), "");
+
+        annotations.toggle_square(square("d4"), Brush::Green);
+        annotations.toggle_arrow(square("e2"), square("e4"), Brush::Green);
+        annotations.toggle_arrow(square("g1"), square("f3"), Brush::Red);
+        assert_eq!(annotations.to_pgn(), "[%csl Gd4][%cal Ge2e4,Rg1f3]");
+    }
+
+    #[test]
+    fn toggling_the_same_mark_removes_it() {
+        let mut annotations = Annotations::default();
+        annotations.toggle_square(square("d4"), Brush::Green);
+        annotations.toggle_square(square("d4"), Brush::Green);
+        annotations.toggle_arrow(square("e2"), square("e4"), Brush::Blue);
+        annotations.toggle_arrow(square("e2"), square("e4"), Brush::Blue);
+        assert!(annotations.is_empty());
+    }
+
+    #[test]
+    fn toggling_in_another_colour_replaces_the_mark() {
+        let mut annotations = Annotations::default();
+        annotations.toggle_square(square("d4"), Brush::Green);
+        annotations.toggle_square(square("d4"), Brush::Red);
+        annotations.toggle_arrow(square("e2"), square("e4"), Brush::Green);
+        annotations.toggle_arrow(square("e2"), square("e4"), Brush::Yellow);
+        assert_eq!(annotations.to_pgn(), "[%csl Rd4][%cal Ye2e4]");
+    }
+
+    #[test]
+    fn ignores_arrows_to_the_same_square() {
+        let mut annotations = Annotations::default();
+        annotations.toggle_arrow(square("e4"), square("e4"), Brush::Green);
+        assert!(annotations.is_empty());
+    }
+}
@@ -0,0 +1,61 @@
+use godot::prelude::*;
+
+#[derive(Clone, Copy)]
+pub struct Arrow {
+    pub from: Vector2,
+    pub to: Vector2,
+    pub color: Color,
+    pub width: f32,
+}
+
+/// Draws arrows over the board. Kept as its own node so the arrows end up above the squares and
+/// pieces.
+#[derive(GodotClass)]
+#[class(base = Node2D)]
+pub struct ArrowLayer {
+    arrows: Vec<Arrow>,
+    base: Base<Node2D>,
+}
+
+#[godot_api]
+impl INode2D for ArrowLayer {
+    fn init(base: Base<Node2D>) -> Self {
+        Self {
+            arrows: Vec::new(),
+            base,
+        }
+    }
+
+    fn draw(&mut self) {
+        for arrow in self.arrows.clone() {
+            // There's no direction to point in
+            if arrow.from == arrow.to {
+                continue;
+            }
+            let direction = (arrow.to - arrow.from).normalized();
+            let normal = Vector2::new(-direction.y, direction.x);
+            let head_length = arrow.width * 2.0;
+            let head_base = arrow.to - direction * head_length;
+
+            self.base_mut()
+                .draw_line_ex(arrow.from, head_base, arrow.color)
+                .width(arrow.width)
+                .done();
+            let head = PackedVector2Array::from(
+                &[
+                    arrow.to,
+                    head_base + normal * arrow.width * 1.2,
+                    head_base - normal * arrow.width * 1.2,
+                ][..],
+            );
+            self.base_mut().draw_colored_polygon(head, arrow.color);
+        }
+    }
+}
+
+impl ArrowLayer {
+    pub fn set_arrows(&mut self, arrows: Vec<Arrow>) {
+        self.arrows = arrows;
+        self.base_mut().queue_redraw();
+    }
+}
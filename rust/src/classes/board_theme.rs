@@ -0,0 +1,77 @@
+use godot::{
+    engine::{IResource, Resource, Texture2D},
+    prelude::*,
+};
+
+/// How a `ChessBoard2D` looks. Save one as a `.tres` file to reuse it across boards.
+#[derive(GodotClass)]
+#[class(base = Resource)]
+pub struct BoardTheme {
+    #[export]
+    pub light_color: Color,
+    #[export]
+    pub dark_color: Color,
+    #[export]
+    /// Used for the light squares instead of `light_color`, if set.
+    pub light_texture: Option<Gd<Texture2D>>,
+    #[export]
+    /// Used for the dark squares instead of `dark_color`, if set.
+    pub dark_texture: Option<Gd<Texture2D>>,
+    #[export]
+    /// Directory with a texture for each piece, named like "White Knight.svg" or "Black Pawn.png".
+    pub piece_set: GString,
+    #[export]
+    pub selected_color: Color,
+    #[export]
+    pub last_move_color: Color,
+    #[export]
+    pub legal_target_color: Color,
+    #[export]
+    pub capture_target_color: Color,
+    #[export]
+    pub check_color: Color,
+    #[export]
+    pub premove_color: Color,
+    #[export]
+    pub mark_color: Color,
+    #[export]
+    pub red_mark_color: Color,
+    #[export]
+    pub yellow_mark_color: Color,
+    #[export]
+    pub blue_mark_color: Color,
+    #[export]
+    pub focus_color: Color,
+    #[export]
+    pub drag_target_color: Color,
+    #[export]
+    pub hint_color: Color,
+    base: Base<Resource>,
+}
+
+#[godot_api]
+impl IResource for BoardTheme {
+    fn init(base: Base<Resource>) -> Self {
+        Self {
+            light_color: Color::WHITE,
+            dark_color: Color::from_html("#1A4F42").unwrap(),
+            light_texture: None,
+            dark_texture: None,
+            piece_set: "res://art/Chess Pieces".into(),
+            selected_color: Color::from_rgba(1.0, 1.0, 0.0, 0.5),
+            last_move_color: Color::from_rgba(1.0, 0.85, 0.0, 0.4),
+            legal_target_color: Color::from_rgba(0.0, 0.0, 0.0, 0.25),
+            capture_target_color: Color::from_rgba(0.0, 0.0, 0.0, 0.25),
+            check_color: Color::from_rgba(1.0, 0.45, 0.0, 0.7),
+            premove_color: Color::from_rgba(0.2, 0.8, 1.0, 0.5),
+            mark_color: Color::from_rgba(0.0, 0.8, 0.2, 0.5),
+            red_mark_color: Color::from_rgba(0.9, 0.1, 0.1, 0.5),
+            yellow_mark_color: Color::from_rgba(1.0, 0.75, 0.0, 0.5),
+            blue_mark_color: Color::from_rgba(0.1, 0.4, 1.0, 0.5),
+            focus_color: Color::from_rgba(0.1, 0.4, 1.0, 0.9),
+            drag_target_color: Color::from_rgba(1.0, 1.0, 1.0, 0.3),
+            hint_color: Color::from_rgba(0.1, 0.5, 1.0, 0.8),
+            base,
+        }
+    }
+}
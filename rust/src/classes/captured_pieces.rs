@@ -0,0 +1,84 @@
+use crate::{classes::ChessBoard2D, types};
+use godot::{
+    engine::{
+        texture_rect::{ExpandMode, StretchMode},
+        HBoxContainer, IHBoxContainer, Label, TextureRect,
+    },
+    prelude::*,
+};
+
+/// Shows the pieces one side of a `ChessBoard2D` has captured, and how far ahead in material that
+/// side is.
+#[derive(GodotClass)]
+#[class(base = HBoxContainer)]
+pub struct CapturedPieces {
+    #[export]
+    board: NodePath,
+    #[export]
+    /// "white" or "black", the side whose captures are shown.
+    color: GString,
+    #[export]
+    piece_size: f32,
+    base: Base<HBoxContainer>,
+}
+
+#[godot_api]
+impl IHBoxContainer for CapturedPieces {
+    fn init(base: Base<HBoxContainer>) -> Self {
+        Self {
+            board: NodePath::default(),
+            color: "white".into(),
+            piece_size: 24.0,
+            base,
+        }
+    }
+
+    fn ready(&mut self) {
+        let mut board = self.base().get_node_as::<Node>(self.board.clone());
+        board.connect(
+            "captures_changed".into(),
+            self.base().callable("on_captures_changed"),
+        );
+        self.on_captures_changed();
+    }
+}
+
+#[godot_api]
+impl CapturedPieces {
+    #[func]
+    fn on_captures_changed(&mut self) {
+        for mut child in self.base().get_children().iter_shared() {
+            self.base_mut().remove_child(child.clone());
+            child.queue_free();
+        }
+
+        let color = if self.color.to_string().to_lowercase() == "black" {
+            types::Color::Black
+        } else {
+            types::Color::White
+        };
+        let board = self.base().get_node_as::<ChessBoard2D>(self.board.clone());
+        let board = board.bind();
+
+        for troop in board.captured_by(color) {
+            let mut texture_rect = TextureRect::new_alloc();
+            if let Some(texture) = board.piece_texture(troop) {
+                texture_rect.set_texture(texture);
+            }
+            texture_rect.set_expand_mode(ExpandMode::IGNORE_SIZE);
+            texture_rect.set_stretch_mode(StretchMode::KEEP_ASPECT_CENTERED);
+            texture_rect.set_custom_minimum_size(Vector2::new(self.piece_size, self.piece_size));
+            self.base_mut().add_child(texture_rect.upcast());
+        }
+
+        let lead = match color {
+            types::Color::White => board.material_difference(),
+            types::Color::Black => -board.material_difference(),
+        };
+        if lead > 0 {
+            let mut label = Label::new_alloc();
+            label.set_text(format!("+{}", lead).into());
+            self.base_mut().add_child(label.upcast());
+        }
+    }
+}
@@ -1,11 +1,25 @@
 use crate::{
-    classes::ChessPiece,
-    types::{self, Board, Move, Piece},
+    annotation::{Annotations, Brush},
+    classes::{
+        Arrow, ArrowLayer, BoardTheme, ChessPiece, Highlight, HighlightLayer, HighlightShape,
+    },
+    clock::{Clock, ClockFormat},
+    engine::{search, Analysis, MoveSearch, SearchLimits, Skill, TimeControl},
+    tablebase::Tablebase,
+    types::{
+        self, index_to_square, is_dark, parse_square, square_name, timeout_result, Board,
+        GameResult, Move, Piece, Termination, Troop,
+    },
 };
 use godot::{
-    engine::{Sprite2D, Texture2D},
+    engine::{
+        global::{HorizontalAlignment, MouseButton, VerticalAlignment},
+        AudioServer, AudioStream, AudioStreamPlayer, Control, InputEvent, InputEventKey,
+        InputEventMouseButton, Label, ProjectSettings, ResourceLoader, Sprite2D, Texture2D,
+    },
     prelude::*,
 };
+use std::{sync::Arc, time::Duration};
 
 #[derive(GodotClass)]
 #[class(base = Node2D)]
@@ -13,247 +27,2015 @@ pub struct ChessBoard2D {
     #[export]
     dark_color: Color,
     #[export]
+    light_color: Color,
+    #[export]
+    /// If set, replaces the board's own colours. Call `switch_theme` to change it once the board
+    /// is ready.
+    board_theme: Option<Gd<BoardTheme>>,
+    light_texture: Option<Gd<Texture2D>>,
+    dark_texture: Option<Gd<Texture2D>>,
+    /// Directory the piece textures are loaded from.
+    piece_set: GString,
+    #[export]
     square_size: f32,
+    #[export]
+    /// Sizes the board to fit the parent control, or the rest of the viewport if the parent isn't
+    /// a control, and keeps it fitting when that's resized. `square_size` is then ignored.
+    fit_to_viewport: bool,
+    #[export]
+    /// Seconds pieces take to slide to their squares. Zero moves them instantly.
+    animation_duration: f64,
+    #[export]
+    /// Shakes pieces dropped where they can't go as they snap back.
+    shake_illegal_drops: bool,
+    #[export]
+    /// Tint for pieces dropped where they can't go, fading as they snap back. White turns it off.
+    illegal_drop_color: Color,
+    #[export]
+    /// Whether black is at the bottom. Call `flip` to change it once the board is ready.
+    flipped: bool,
+    #[export]
+    /// Shows the board from black's side when the computer plays white.
+    auto_flip: bool,
+    #[export]
+    /// Labels the files and ranks along the bottom and left edges. Call `set_coordinates_shown`
+    /// to change it once the board is ready.
+    show_coordinates: bool,
+    #[export]
+    /// Played for moves that don't have a sound of their own below.
+    move_sound: Option<Gd<AudioStream>>,
+    #[export]
+    capture_sound: Option<Gd<AudioStream>>,
+    #[export]
+    castle_sound: Option<Gd<AudioStream>>,
+    #[export]
+    check_sound: Option<Gd<AudioStream>>,
+    #[export]
+    promotion_sound: Option<Gd<AudioStream>>,
+    #[export]
+    game_end_sound: Option<Gd<AudioStream>>,
+    #[export]
+    /// Played when a piece is dropped, clicked or typed somewhere it can't go.
+    illegal_sound: Option<Gd<AudioStream>>,
+    #[export]
+    /// Audio bus the sounds play on. Call `set_sound_muted` to mute it.
+    sound_bus: GString,
+    sound_player: Option<Gd<AudioStreamPlayer>>,
+    coordinates: Vec<Gd<Label>>,
     pub board: Board,
     #[export]
     /// If blank, the normal starting position is used.
     starting_fen: GString,
+    #[export]
+    /// Directory containing Syzygy tablebase files. If blank, tablebases aren't used.
+    tablebase_directory: GString,
+    #[export]
+    /// "white" or "black" to have the computer play that side. If blank, both sides are human.
+    ai_color: GString,
+    #[export]
+    /// "white" or "black" for a side played by someone else through `make_move`, like a player
+    /// over the network. Its pieces can't be moved on this board.
+    remote_color: GString,
+    #[export]
+    /// Lets the player queue moves while the computer or remote player is thinking.
+    allow_premoves: bool,
+    /// Queued moves, played in order once it's the player's turn.
+    premoves: Vec<Move>,
+    #[export]
+    /// From 0 to 20. 20 is full strength.
+    skill_level: i64,
+    #[export]
+    /// If above zero, overrides `skill_level` with a level roughly matching this rating.
+    target_elo: i64,
+    #[export]
+    /// PGN-style time control, like "300+2" or "40/5400+30:1800+30". Use "d" instead of "+" for a
+    /// simple delay, or "b" for a Bronstein delay. If blank, the game is untimed.
+    time_control: GString,
+    clock: Option<Clock>,
+    result: Option<(GameResult, Termination)>,
+    /// Every position so far, for spotting repetitions.
+    positions: Vec<String>,
+    /// The board before each move so far, with the move, for undoing.
+    history: Vec<(Board, Move)>,
+    /// How many moves into the game the shown position is, while looking back at an earlier one.
+    viewed_ply: Option<usize>,
+    /// A pawn move to the last rank, waiting for the player to pick a piece.
+    pending_promotion: Option<Move>,
+    #[export]
+    /// How many of the best lines to report while analyzing.
+    analysis_lines: i64,
+    analysis: Option<Analysis>,
+    #[export]
+    /// Highlight for the piece being moved.
+    selected_color: Color,
+    #[export]
+    last_move_color: Color,
+    #[export]
+    /// Colour of the dots on empty squares the piece being moved can go to.
+    legal_target_color: Color,
+    #[export]
+    /// Size of the dots, as a fraction of `square_size`.
+    legal_target_size: f32,
+    #[export]
+    /// Colour of the rings around pieces the piece being moved can capture.
+    capture_target_color: Color,
+    #[export]
+    /// Width of the rings, as a fraction of `square_size`.
+    capture_target_width: f32,
+    #[export]
+    /// Highlight for the king's square while it's in check.
+    check_color: Color,
+    #[export]
+    /// Highlight for premoves, waiting to be played.
+    premove_color: Color,
+    #[export]
+    /// Colour of green arrows and marked squares, drawn with a right-click. Green is the default.
+    mark_color: Color,
+    #[export]
+    /// Colour of red arrows and marks, drawn with shift or ctrl held.
+    red_mark_color: Color,
+    #[export]
+    /// Colour of yellow arrows and marks, drawn with shift and alt held.
+    yellow_mark_color: Color,
+    #[export]
+    /// Colour of blue arrows and marks, drawn with alt held.
+    blue_mark_color: Color,
+    #[export]
+    /// Colour of the ring around the square the keyboard cursor is on.
+    focus_color: Color,
+    /// The square picked with the arrow keys. Hidden until they're first used.
+    cursor: Option<usize>,
+    #[export]
+    /// Highlight for the square under a dragged piece.
+    drag_target_color: Color,
+    drag_target: Option<usize>,
+    #[export]
+    /// Opacity of the copy of a dragged piece left on its square. Zero leaves none.
+    ghost_opacity: f32,
+    ghost: Option<Gd<Sprite2D>>,
+    /// The piece being dragged. It's only bound while its own input is handled, and that ends the
+    /// drag first, so it can be let go of from anywhere else.
+    dragged: Option<Gd<ChessPiece>>,
+    annotations: Annotations,
+    /// Where the right mouse button was pressed, to draw an arrow from.
+    annotation_start: Option<usize>,
+    highlight_layer: Option<Gd<HighlightLayer>>,
+    #[export]
+    hint_color: Color,
+    #[export]
+    /// Whether hints also highlight the start and end squares, on top of the arrow.
+    hint_highlights_squares: bool,
+    hint: Option<Move>,
+    hint_search: Option<MoveSearch>,
+    arrow_layer: Option<Gd<ArrowLayer>>,
+    tablebase: Option<Arc<Tablebase>>,
+    tablebase_label: Option<Gd<Label>>,
+    ai_search: Option<MoveSearch>,
     squares: Vec<Gd<Sprite2D>>,
     pieces: Vec<Option<Gd<ChessPiece>>>,
-    last_picked: usize,
-    last_placed: usize,
-    current_picked: usize,
+    last_move: Option<Move>,
+    /// The piece being dragged or selected, whose moves are highlighted.
+    current_picked: Option<usize>,
+    /// The piece clicked on, waiting for a click on where it should go.
+    selected: Option<usize>,
     base: Base<Node2D>,
 }
 
 #[godot_api]
 impl INode2D for ChessBoard2D {
     fn init(base: Base<Node2D>) -> Self {
+        // The defaults are a theme's, so the two can't drift apart
+        let theme = BoardTheme::new_gd();
+        let theme = theme.bind();
         Self {
-            dark_color: Color::from_html("#1A4F42").unwrap(),
+            dark_color: theme.dark_color,
+            light_color: theme.light_color,
+            board_theme: None,
+            light_texture: None,
+            dark_texture: None,
+            piece_set: theme.piece_set.clone(),
             square_size: 70.0,
+            fit_to_viewport: false,
+            animation_duration: 0.15,
+            shake_illegal_drops: true,
+            illegal_drop_color: Color::from_rgba(1.0, 0.4, 0.4, 1.0),
+            flipped: false,
+            auto_flip: true,
+            show_coordinates: true,
+            move_sound: None,
+            capture_sound: None,
+            castle_sound: None,
+            check_sound: None,
+            promotion_sound: None,
+            game_end_sound: None,
+            illegal_sound: None,
+            sound_bus: "Sound".into(),
+            sound_player: None,
+            coordinates: Vec::new(),
             board: Board::starting(),
             squares: Vec::new(),
             starting_fen: "".into(),
+            tablebase_directory: "".into(),
+            ai_color: "".into(),
+            remote_color: "".into(),
+            allow_premoves: true,
+            premoves: Vec::new(),
+            skill_level: 20,
+            target_elo: 0,
+            time_control: "".into(),
+            clock: None,
+            result: None,
+            positions: Vec::new(),
+            history: Vec::new(),
+            viewed_ply: None,
+            pending_promotion: None,
+            analysis_lines: 3,
+            analysis: None,
+            selected_color: theme.selected_color,
+            last_move_color: theme.last_move_color,
+            legal_target_color: theme.legal_target_color,
+            legal_target_size: 0.3,
+            capture_target_color: theme.capture_target_color,
+            capture_target_width: 0.1,
+            check_color: theme.check_color,
+            premove_color: theme.premove_color,
+            mark_color: theme.mark_color,
+            red_mark_color: theme.red_mark_color,
+            yellow_mark_color: theme.yellow_mark_color,
+            blue_mark_color: theme.blue_mark_color,
+            focus_color: theme.focus_color,
+            cursor: None,
+            drag_target_color: theme.drag_target_color,
+            drag_target: None,
+            ghost_opacity: 0.3,
+            ghost: None,
+            dragged: None,
+            annotations: Annotations::default(),
+            annotation_start: None,
+            highlight_layer: None,
+            hint_color: theme.hint_color,
+            hint_highlights_squares: false,
+            hint: None,
+            hint_search: None,
+            arrow_layer: None,
+            tablebase: None,
+            tablebase_label: None,
+            ai_search: None,
             pieces: vec![None; 64],
-            last_picked: 0,
-            last_placed: 0,
-            current_picked: 0,
+            last_move: None,
+            current_picked: None,
+            selected: None,
             base,
         }
     }
 
     fn ready(&mut self) {
-        let fen = if self.starting_fen.is_empty() {
-            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".into()
-        } else {
-            self.starting_fen.clone().to_string()
-        };
+        if self.fit_to_viewport {
+            self.square_size = self.fitted_square_size();
+            let callable = self.base().callable("on_resized");
+            match self.parent_control() {
+                Some(mut parent) => {
+                    parent.connect("resized".into(), callable);
+                }
+                None => {
+                    let mut viewport = self.base().get_viewport().unwrap();
+                    viewport.connect("size_changed".into(), callable);
+                }
+            }
+        }
 
-        self.board = Board::from_fen(&fen).unwrap();
+        self.take_theme();
+        if self.auto_flip && self.ai_color() == Some(types::Color::White) {
+            self.flipped = true;
+        }
+        for i in 0..64 {
+            self.add_square(i);
+        }
+        let highlight_layer = HighlightLayer::new_alloc();
+        self.highlight_layer = Some(highlight_layer.clone());
+        self.base_mut().add_child(highlight_layer.upcast());
+        if self.show_coordinates {
+            self.add_coordinates();
+        }
 
-        for rank in 0..8 {
-            for file in 0..8 {
-                let dark = file % 2 != rank % 2;
-                self.add_square(
-                    dark,
-                    file as f32 * self.square_size,
-                    rank as f32 * self.square_size,
-                );
+        let mut arrow_layer = ArrowLayer::new_alloc();
+        arrow_layer.set_z_index(1);
+        self.arrow_layer = Some(arrow_layer.clone());
+        self.base_mut().add_child(arrow_layer.upcast());
+
+        if !self.tablebase_directory.is_empty() {
+            let directory = ProjectSettings::singleton()
+                .globalize_path(self.tablebase_directory.clone())
+                .to_string();
+            match Tablebase::open(&directory) {
+                Ok(tablebase) => self.tablebase = Some(Arc::new(tablebase)),
+                Err(e) => godot_error!("Couldn't load tablebases: {}", e),
             }
+
+            let mut label = Label::new_alloc();
+            label.set_position(Vector2::new(0.0, 8.0 * self.square_size));
+            self.tablebase_label = Some(label.clone());
+            self.base_mut().add_child(label.upcast());
         }
 
-        let troops = self.board.troops;
-        for (i, troop) in troops.iter().enumerate() {
-            if let Some(troop) = troop {
-                let square = &self.squares[i];
-                let texture = load::<Texture2D>(format!("res://art/Chess Pieces/{}.svg", troop));
-                let mut piece = ChessPiece::new_alloc();
-                piece.set_texture(texture);
-                piece.set_scale(Vector2::new(
-                    self.square_size / 50.0,
-                    self.square_size / 50.0,
-                ));
-                piece.set_position(
-                    square.get_position()
-                        + Vector2::new(self.square_size / 2.0, self.square_size / 2.0),
-                );
-                piece.bind_mut().index = i;
+        let mut sound_player = AudioStreamPlayer::new_alloc();
+        sound_player.set_bus(self.sound_bus.clone().into());
+        self.sound_player = Some(sound_player.clone());
+        self.base_mut().add_child(sound_player.upcast());
+
+        self.load(self.starting_board());
+    }
+
+    fn process(&mut self, delta: f64) {
+        self.run_clock(delta);
+        self.receive_ai_move();
+        self.receive_hint();
+        self.report_analysis();
+    }
+
+    fn unhandled_input(&mut self, event: Gd<InputEvent>) {
+        if let Ok(event) = event.clone().try_cast::<InputEventMouseButton>() {
+            if event.get_button_index() == MouseButton::RIGHT {
+                self.annotate(event);
+                return;
+            }
 
-                self.pieces[i] = Some(piece.clone());
-                self.base_mut().add_child(piece.upcast());
+            if event.get_button_index() == MouseButton::LEFT && event.is_pressed() {
+                self.clear_annotations();
+                if self.selected.is_some() {
+                    self.click(event.get_position());
+                } else if !self.premoves.is_empty() {
+                    self.cancel_premoves();
+                }
+            }
+        } else if event.clone().try_cast::<InputEventKey>().is_ok() {
+            let pressed = |action: &str| {
+                event
+                    .is_action_pressed_ex(action.into())
+                    .allow_echo(true)
+                    .done()
+            };
+            if pressed("ui_left") {
+                self.move_cursor(-1, 0);
+            } else if pressed("ui_right") {
+                self.move_cursor(1, 0);
+            } else if pressed("ui_up") {
+                self.move_cursor(0, -1);
+            } else if pressed("ui_down") {
+                self.move_cursor(0, 1);
+            } else if event.is_action_pressed("ui_accept".into()) && self.cursor.is_some() {
+                self.press_cursor();
+            } else if event.is_action_pressed("ui_cancel".into()) && self.selected.is_some() {
+                self.unpick();
+                self.announce("Selection cancelled".into());
+            } else {
+                return;
             }
+            self.base().get_viewport().unwrap().set_input_as_handled();
         }
     }
 }
 
 #[godot_api]
 impl ChessBoard2D {
-    fn add_square(&mut self, dark: bool, x: f32, y: f32) {
-        let texture = load::<Texture2D>("res://art/White Square.png");
+    /// Emitted after every move, with the move in UCI and SAN and the new position's FEN.
+    #[signal]
+    fn move_made(uci: GString, san: GString, fen: GString);
+
+    /// `piece` is like "Black Knight", and `square` is where it was captured, like "e5".
+    #[signal]
+    fn piece_captured(piece: GString, square: GString);
+
+    /// Emitted when a move gives check, with the checked king's square.
+    #[signal]
+    fn check(square: GString);
+
+    /// `result` is "1-0", "0-1" or "1/2-1/2", and `reason` is like "Checkmate".
+    #[signal]
+    fn game_over(result: GString, reason: GString);
+
+    /// `color` is "White" or "Black", whoever is to move now.
+    #[signal]
+    fn turn_changed(color: GString);
+
+    /// Emitted when the player drops a pawn on the last rank. The move is finished by calling
+    /// `promote`, which handlers may call straight away since this is emitted deferred. If nothing
+    /// is connected, pawns promote to a queen straight away.
+    #[signal]
+    fn promotion_requested(square: GString);
+
+    /// Emitted while analyzing, whenever the search gets deeper. Each line is a dictionary with
+    /// `score` in centipawns and `mate` in moves (zero if there's no mate), both from white's
+    /// point of view, plus `depth` and `moves`, the principal variation in SAN.
+    #[signal]
+    fn analysis_updated(lines: Array<Dictionary>);
+
+    /// Emitted every frame while a clock is running, with each side's remaining time in seconds.
+    #[signal]
+    fn clock_updated(white_seconds: f64, black_seconds: f64);
+
+    /// Emitted when queued premoves are dropped, either by the player or because the next one
+    /// turned out to be illegal.
+    #[signal]
+    fn premoves_cancelled();
+
+    /// Emitted when the captured pieces or the material balance may have changed, or the piece
+    /// textures have.
+    #[signal]
+    fn captures_changed();
+
+    /// Emitted when moves are made or taken back, a new game starts, or an earlier position is
+    /// shown.
+    #[signal]
+    fn history_changed();
+
+    /// Emitted with a description in words of each move, the square under the keyboard cursor,
+    /// or why something didn't work, for screen readers. Moves are described like "Knight takes
+    /// e5, check".
+    #[signal]
+    fn announcement(text: GString);
+
+    /// Emitted when a dropped piece goes back to its square, `from`, because it can't go to `to`.
+    /// `to` is blank if it was dropped off the board.
+    #[signal]
+    fn piece_snapped_back(piece: GString, from: GString, to: GString);
+
+    /// Emitted when `color` runs out of time. `result` is "1-0", "0-1" or "1/2-1/2".
+    #[signal]
+    fn flagged(color: GString, result: GString);
+
+    #[func]
+    fn cancel_premoves(&mut self) {
+        if self.premoves.is_empty() {
+            return;
+        }
+        self.premoves.clear();
+        self.update_highlights();
+        self.base_mut()
+            .emit_signal("premoves_cancelled".into(), &[]);
+    }
+
+    /// Marks `square`, like "e4", or unmarks it if it already has that mark. `color` is "green",
+    /// "red", "yellow" or "blue", or its first letter.
+    #[func]
+    fn mark_square(&mut self, square: GString, color: GString) {
+        let (Ok(square), Some(brush)) = (
+            parse_square(&square.to_string()),
+            Brush::parse(&color.to_string()),
+        ) else {
+            godot_error!("Can't mark {} in {}", square, color);
+            return;
+        };
+        self.annotations.toggle_square(square, brush);
+        self.update_highlights();
+    }
+
+    /// Like `mark_square`, for an arrow between two squares.
+    #[func]
+    fn draw_arrow(&mut self, from: GString, to: GString, color: GString) {
+        let (Ok(from), Ok(to), Some(brush)) = (
+            parse_square(&from.to_string()),
+            parse_square(&to.to_string()),
+            Brush::parse(&color.to_string()),
+        ) else {
+            godot_error!("Can't draw an arrow from {} to {} in {}", from, to, color);
+            return;
+        };
+        if from == to {
+            godot_error!("Can't draw an arrow from {} to itself", square_name(from));
+            return;
+        }
+        self.annotations.toggle_arrow(from, to, brush);
+        self.update_arrows();
+    }
+
+    /// Pieces `color` has captured this game, like "Black Knight", lowest value first.
+    #[func]
+    fn get_captured_by(&self, color: GString) -> PackedStringArray {
+        let mut captured = PackedStringArray::new();
+        match parse_color(&color) {
+            Some(color) => {
+                for troop in self.captured_by(color) {
+                    captured.push(troop.to_string().into());
+                }
+            }
+            None => godot_error!("Invalid color {}", color),
+        }
+        captured
+    }
+
+    /// White's material minus black's, counting a pawn as 1, a knight or bishop as 3 and so on.
+    /// Only what changed during the game counts, not an imbalance in a position set with
+    /// `set_fen`.
+    #[func]
+    fn get_material_difference(&self) -> i64 {
+        self.material_difference() as i64
+    }
+
+    /// Removes all arrows and marks.
+    #[func]
+    fn clear_annotations(&mut self) {
+        if self.annotations.is_empty() {
+            return;
+        }
+        self.annotations.clear();
+        self.update_arrows();
+        self.update_highlights();
+    }
+
+    /// The arrows and marks as PGN comment commands, like `[%csl Gd4][%cal Ge2e4,Rg1f3]`.
+    #[func]
+    fn get_annotations(&self) -> GString {
+        self.annotations.to_pgn().into()
+    }
+
+    /// Draws an arrow from where the right button was pressed to where it's released, or marks
+    /// the square if they're the same.
+    fn annotate(&mut self, event: Gd<InputEventMouseButton>) {
+        let square = self.square_at(event.get_position());
+        if event.is_pressed() {
+            self.annotation_start = square;
+            return;
+        }
+
+        let (Some(start), Some(end)) = (self.annotation_start.take(), square) else {
+            return;
+        };
+        let brush = match (
+            event.is_shift_pressed() || event.is_ctrl_pressed(),
+            event.is_alt_pressed(),
+        ) {
+            (false, false) => Brush::Green,
+            (true, false) => Brush::Red,
+            (true, true) => Brush::Yellow,
+            (false, true) => Brush::Blue,
+        };
+
+        if start == end {
+            self.annotations.toggle_square(start, brush);
+            self.update_highlights();
+        } else {
+            self.annotations.toggle_arrow(start, end, brush);
+            self.update_arrows();
+        }
+    }
+
+    fn brush_color(&self, brush: Brush) -> Color {
+        match brush {
+            Brush::Green => self.mark_color,
+            Brush::Red => self.red_mark_color,
+            Brush::Yellow => self.yellow_mark_color,
+            Brush::Blue => self.blue_mark_color,
+        }
+    }
+
+    #[func]
+    fn get_fen(&self) -> GString {
+        self.board.to_fen().into()
+    }
+
+    /// Starts a new game from `fen`. Returns false if the FEN is invalid.
+    #[func]
+    fn set_fen(&mut self, fen: GString) -> bool {
+        match Board::from_fen(&fen.to_string()) {
+            Ok(board) => {
+                self.load(board);
+                true
+            }
+            Err(e) => {
+                godot_error!("Invalid FEN: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Turns the board around.
+    #[func]
+    fn flip(&mut self) {
+        self.flipped = !self.flipped;
+        self.lay_out();
+    }
+
+    /// Adds or removes the rank and file labels.
+    #[func]
+    fn set_coordinates_shown(&mut self, shown: bool) {
+        self.show_coordinates = shown;
+        if shown && self.coordinates.is_empty() {
+            self.add_coordinates();
+        } else if !shown {
+            for mut label in self.coordinates.drain(..) {
+                label.queue_free();
+            }
+        }
+    }
+
+    /// Changes how the board looks. Passing null keeps the current look.
+    #[func]
+    fn switch_theme(&mut self, theme: Option<Gd<BoardTheme>>) {
+        self.board_theme = theme;
+        self.take_theme();
+
+        let troops = self.board.troops;
+        for (i, troop) in troops.iter().enumerate() {
+            if let (Some(troop), Some(mut piece)) = (troop, self.pieces[i].clone()) {
+                if let Some(texture) = self.piece_texture(*troop) {
+                    piece.set_texture(texture);
+                }
+            }
+        }
+        self.lay_out();
+        self.base_mut().emit_signal("captures_changed".into(), &[]);
+    }
+
+    #[func]
+    fn on_resized(&mut self) {
+        let square_size = self.fitted_square_size();
+        if square_size != self.square_size {
+            self.square_size = square_size;
+            self.lay_out();
+        }
+    }
+
+    /// Starts a new game from `starting_fen`.
+    #[func]
+    fn reset(&mut self) {
+        self.load(self.starting_board());
+    }
+
+    /// Legal moves for the piece on `square`, like "e2", in UCI. Empty if it isn't the piece's
+    /// turn.
+    #[func]
+    fn legal_moves_from(&self, square: GString) -> PackedStringArray {
+        let mut moves = PackedStringArray::new();
+        let Ok(square) = parse_square(&square.to_string()) else {
+            return moves;
+        };
+        for m in self
+            .board
+            .legal_moves()
+            .iter()
+            .filter(|m| m.start == square)
+        {
+            moves.push(m.to_uci().into());
+        }
+        moves
+    }
+
+    /// Plays a move in UCI, like "e2e4" or "e7e8q", for whichever side is to move. Returns false
+    /// if it isn't legal.
+    #[func]
+    fn make_move(&mut self, uci: GString) -> bool {
+        let m = match Move::from_uci(&uci.to_string()) {
+            Ok(m) => m,
+            Err(e) => {
+                godot_error!("{}", e);
+                return false;
+            }
+        };
+        if self.result.is_some() || !self.board.legal_moves().contains(&m) {
+            return false;
+        }
+
+        self.ai_search = None;
+        self.cancel_promotion();
+        self.play_move(m);
+        true
+    }
+
+    /// Plays a move typed by the player, in SAN like "Nf3" or UCI like "g1f3". Returns false, and
+    /// announces why, if it can't be played.
+    #[func]
+    pub fn enter_move(&mut self, text: GString) -> bool {
+        let problem = if self.result.is_some() {
+            "The game is over".to_string()
+        } else if self.viewed_ply.is_some() {
+            "An earlier position is being shown".to_string()
+        } else if self.pending_promotion.is_some() {
+            "Choose a piece to promote to first".to_string()
+        } else if self.opponent_color() == Some(self.board.turn) {
+            "It's not your turn".to_string()
+        } else {
+            match self.board.parse_move(&text.to_string()) {
+                Ok(m) => {
+                    self.ai_search = None;
+                    self.play_move(m);
+                    return true;
+                }
+                Err(e) => e,
+            }
+        };
+        self.announce(problem);
+        self.play_sound(self.illegal_sound.clone());
+        false
+    }
+
+    #[func]
+    fn set_sound_muted(&mut self, muted: bool) {
+        let mut audio_server = AudioServer::singleton();
+        let bus = audio_server.get_bus_index(self.sound_bus.clone().into());
+        if bus < 0 {
+            godot_error!("No audio bus named {}", self.sound_bus);
+            return;
+        }
+        audio_server.set_bus_mute(bus, muted);
+    }
+
+    #[func]
+    fn is_sound_muted(&self) -> bool {
+        let audio_server = AudioServer::singleton();
+        let bus = audio_server.get_bus_index(self.sound_bus.clone().into());
+        bus >= 0 && audio_server.is_bus_mute(bus)
+    }
+
+    /// Describes the shown position in words, like "White to move. White: King e1, ...".
+    #[func]
+    fn describe_position(&self) -> GString {
+        self.shown_board().describe().into()
+    }
+
+    /// Takes back the last move. Against the computer, its reply is taken back as well, so it's
+    /// the player's turn again. The clocks keep their times. Returns false if there's nothing to
+    /// undo.
+    #[func]
+    fn undo(&mut self) -> bool {
+        let shown = self.shown_board().troops;
+        let Some((board, _)) = self.history.pop() else {
+            return false;
+        };
+        self.board = board;
+        self.positions.pop();
+        if self.ai_color() == Some(self.board.turn) {
+            if let Some((board, _)) = self.history.pop() {
+                self.board = board;
+                self.positions.pop();
+            }
+        }
+
+        self.last_move = self.history.last().map(|(_, m)| *m);
+        self.viewed_ply = None;
+        self.result = None;
+        self.pending_promotion = None;
+        self.premoves.clear();
+        self.selected = None;
+        self.current_picked = None;
+        self.ai_search = None;
+        self.hint = None;
+        self.hint_search = None;
+        let turn = self.board.turn;
+        if let Some(clock) = &mut self.clock {
+            clock.start(turn);
+        }
+        self.refresh(shown);
+        self.base_mut().emit_signal(
+            "turn_changed".into(),
+            &[GString::from(turn.to_string()).to_variant()],
+        );
+        self.start_ai_if_needed();
+        true
+    }
+
+    /// Shows the position after `ply` half-moves, or the current one if `ply` is the number of
+    /// moves played so far. No moves can be made while an earlier position is shown, and the board
+    /// goes back to the current one when the opponent moves.
+    #[func]
+    fn view_ply(&mut self, ply: i64) {
+        let Ok(ply) = usize::try_from(ply) else {
+            return;
+        };
+        if ply > self.history.len() {
+            return;
+        }
+        let viewed_ply = Some(ply).filter(|ply| *ply < self.history.len());
+        if viewed_ply == self.viewed_ply {
+            return;
+        }
+
+        let shown = self.shown_board().troops;
+        self.viewed_ply = viewed_ply;
+        self.selected = None;
+        self.current_picked = None;
+        self.refresh(shown);
+    }
+
+    /// How many half-moves into the game the shown position is.
+    #[func]
+    fn get_viewed_ply(&self) -> i64 {
+        self.viewed_ply() as i64
+    }
+
+    /// Every move so far, in SAN.
+    #[func]
+    fn get_san_moves(&self) -> PackedStringArray {
+        let mut moves = PackedStringArray::new();
+        for san in self.san_moves() {
+            moves.push(san.into());
+        }
+        moves
+    }
+
+    #[func]
+    fn is_game_over(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// The piece on `square`, like "White Knight", or blank if it's empty.
+    #[func]
+    fn get_piece_at(&self, square: GString) -> GString {
+        match parse_square(&square.to_string()) {
+            Ok(square) => self.board.troops[square]
+                .map(|troop| troop.to_string())
+                .unwrap_or_default()
+                .into(),
+            Err(_) => "".into(),
+        }
+    }
+
+    /// Finishes a promotion after `promotion_requested`. `piece` is "queen", "rook", "bishop" or
+    /// "knight", or its letter. Returns false if no promotion is waiting, like when the game ended
+    /// while the player was choosing.
+    #[func]
+    fn promote(&mut self, piece: GString) -> bool {
+        let Some(m) = self.pending_promotion.filter(|_| self.result.is_none()) else {
+            return false;
+        };
+        let piece = match piece.to_string().to_lowercase().as_str() {
+            "q" | "queen" => Piece::Queen,
+            "r" | "rook" => Piece::Rook,
+            "b" | "bishop" => Piece::Bishop,
+            "n" | "knight" => Piece::Knight,
+            _ => {
+                godot_error!("Can't promote to {}", piece);
+                return false;
+            }
+        };
+
+        self.pending_promotion = None;
+        self.play_move(Move {
+            promotion: Some(piece),
+            ..m
+        });
+        true
+    }
+
+    /// Keeps searching the current position in the background, restarting after every move.
+    #[func]
+    fn start_analysis(&mut self) {
+        self.analysis = Some(Analysis::start(
+            self.board,
+            self.analysis_lines.max(1) as usize,
+        ));
+    }
+
+    #[func]
+    fn stop_analysis(&mut self) {
+        self.analysis = None;
+    }
+
+    #[func]
+    fn is_analyzing(&self) -> bool {
+        self.analysis.is_some()
+    }
+
+    /// Shows the best move for the player as an arrow, after a short search. Doesn't play it.
+    #[func]
+    fn show_hint(&mut self) {
+        if self.result.is_some()
+            || self.ai_color() == Some(self.board.turn)
+            || self.board.legal_moves().is_empty()
+        {
+            return;
+        }
+
+        let board = self.board;
+        self.hint_search = Some(MoveSearch::start(move |stop| {
+            let limits = SearchLimits {
+                depth: Some(4),
+                nodes: Some(200_000),
+                stop: Some(stop),
+                ..Default::default()
+            };
+            search(&board, &limits).best_move()
+        }));
+    }
+
+    #[func]
+    fn clear_hint(&mut self) {
+        self.hint_search = None;
+        if self.hint.take().is_some() {
+            self.update_arrows();
+            self.update_highlights();
+        }
+    }
+
+    fn receive_hint(&mut self) {
+        let Some(hint) = self.hint_search.as_ref().and_then(|s| s.result()) else {
+            return;
+        };
+        self.hint_search = None;
+        self.hint = hint;
+        self.update_arrows();
+        self.update_highlights();
+    }
+
+    fn update_arrows(&mut self) {
+        let mut arrows = Vec::new();
+        if let Some(hint) = self.hint {
+            arrows.push(Arrow {
+                from: self.square_center(hint.start),
+                to: self.square_center(hint.end),
+                color: self.hint_color,
+                width: self.square_size / 5.0,
+            });
+        }
+        for (from, to, brush) in &self.annotations.arrows {
+            arrows.push(Arrow {
+                from: self.square_center(*from),
+                to: self.square_center(*to),
+                color: self.brush_color(*brush),
+                width: self.square_size / 6.0,
+            });
+        }
+
+        if let Some(arrow_layer) = &mut self.arrow_layer {
+            arrow_layer.bind_mut().set_arrows(arrows);
+        }
+    }
+
+    fn report_analysis(&mut self) {
+        let Some(lines) = self.analysis.as_ref().and_then(|a| a.latest()) else {
+            return;
+        };
+
+        let mut array = Array::<Dictionary>::new();
+        for line in lines {
+            let mut moves = PackedStringArray::new();
+            for m in line.moves {
+                moves.push(m.into());
+            }
+            array.push(dict! {
+                "score": line.score,
+                "mate": line.mate.unwrap_or(0),
+                "depth": line.depth as i64,
+                "moves": moves,
+            });
+        }
+
+        self.base_mut()
+            .emit_signal("analysis_updated".into(), &[array.to_variant()]);
+    }
+
+    fn run_clock(&mut self, delta: f64) {
+        let Some(clock) = &mut self.clock else {
+            return;
+        };
+        if clock.running().is_none() {
+            return;
+        }
+
+        let flagged = clock.tick(Duration::from_secs_f64(delta));
+        let white = clock.remaining(types::Color::White).as_secs_f64();
+        let black = clock.remaining(types::Color::Black).as_secs_f64();
+        self.base_mut().emit_signal(
+            "clock_updated".into(),
+            &[white.to_variant(), black.to_variant()],
+        );
+
+        if let Some(color) = flagged {
+            let (result, termination) = timeout_result(&self.board, color);
+            self.base_mut().emit_signal(
+                "flagged".into(),
+                &[
+                    GString::from(color.to_string()).to_variant(),
+                    GString::from(result.to_string()).to_variant(),
+                ],
+            );
+            self.end_game(result, termination);
+        }
+    }
+
+    fn end_game(&mut self, result: GameResult, termination: Termination) {
+        self.result = Some((result, termination));
+        self.cancel_promotion();
+        self.premoves.clear();
+        self.ai_search = None;
+        if let Some(clock) = &mut self.clock {
+            clock.stop();
+        }
+
+        self.base_mut().emit_signal(
+            "game_over".into(),
+            &[
+                GString::from(result.to_string()).to_variant(),
+                GString::from(termination.to_string()).to_variant(),
+            ],
+        );
+        self.announce(format!("Game over, {}, {}", termination, result));
+        self.play_sound(self.game_end_sound.clone());
+    }
+
+    /// Drops the promotion waiting for a piece to be picked, sliding the pawn back to its square.
+    fn cancel_promotion(&mut self) {
+        let Some(m) = self.pending_promotion.take() else {
+            return;
+        };
+        let center = self.square_center(m.start);
+        if let Some(piece) = &mut self.pieces[m.start] {
+            ChessPiece::slide(piece, center, self.animation_duration);
+        }
+    }
+
+    fn starting_board(&self) -> Board {
+        if self.starting_fen.is_empty() {
+            return Board::starting();
+        }
+        match Board::from_fen(&self.starting_fen.to_string()) {
+            Ok(board) => board,
+            Err(e) => {
+                godot_error!("Invalid starting FEN: {}", e);
+                Board::starting()
+            }
+        }
+    }
+
+    /// Starts a new game from `board`.
+    fn load(&mut self, board: Board) {
+        let shown = self.shown_board().troops;
+        self.board = board;
+        self.positions = vec![board.position_key()];
+        self.history.clear();
+        self.viewed_ply = None;
+        self.result = None;
+        self.pending_promotion = None;
+        self.premoves.clear();
+        self.selected = None;
+        self.current_picked = None;
+        self.last_move = None;
+        self.ai_search = None;
+        self.hint = None;
+        self.hint_search = None;
+
+        self.clock = None;
+        if !self.time_control.is_empty() {
+            match ClockFormat::parse(&self.time_control.to_string()) {
+                Ok(format) => {
+                    let mut clock = Clock::new(format);
+                    clock.start(board.turn);
+                    self.clock = Some(clock);
+                }
+                Err(e) => godot_error!("Invalid time control: {}", e),
+            }
+        }
+
+        self.refresh(shown);
+        self.base_mut().emit_signal(
+            "turn_changed".into(),
+            &[GString::from(board.turn.to_string()).to_variant()],
+        );
+        self.start_ai_if_needed();
+    }
+
+    /// Updates the piece nodes and everything else shown for the position, for when it's changed
+    /// other than by playing a move. `shown` is what the piece nodes showed before. Pieces that
+    /// are still around slide to their new squares, and the rest fade in or out.
+    fn refresh(&mut self, shown: [Option<Troop>; 64]) {
+        self.cancel_drag();
+        let duration = self.animation_duration;
+        let troops = self.shown_board().troops;
+        let mut pieces = vec![None; 64];
+        let mut spare = Vec::new();
+        for (i, piece) in self.pieces.iter_mut().enumerate() {
+            if let Some(piece) = piece.take() {
+                if troops[i].is_some() && shown[i] == troops[i] {
+                    pieces[i] = Some(piece);
+                } else {
+                    spare.push((shown[i], piece));
+                }
+            }
+        }
+
+        for (i, troop) in troops.iter().enumerate() {
+            let Some(troop) = *troop else {
+                continue;
+            };
+            let center = self.square_center(i);
+
+            if let Some(mut piece) = pieces[i].clone() {
+                // Might have been left somewhere else, like for a promotion
+                ChessPiece::slide(&mut piece, center, duration);
+                continue;
+            }
+
+            let nearest = spare
+                .iter()
+                .enumerate()
+                .filter(|(_, (shown, _))| *shown == Some(troop))
+                .min_by(|(_, (_, a)), (_, (_, b))| {
+                    let a = a.get_position().distance_squared_to(center);
+                    let b = b.get_position().distance_squared_to(center);
+                    a.total_cmp(&b)
+                })
+                .map(|(n, _)| n);
+            let piece = match nearest {
+                Some(n) => {
+                    let (_, mut piece) = spare.swap_remove(n);
+                    ChessPiece::slide(&mut piece, center, duration);
+                    piece
+                }
+                None => {
+                    let mut piece = ChessPiece::new_alloc();
+                    if let Some(texture) = self.piece_texture(troop) {
+                        piece.set_texture(texture);
+                    }
+                    piece.set_scale(self.piece_scale(&piece));
+                    piece.set_position(center);
+                    self.base_mut().add_child(piece.clone().upcast());
+                    ChessPiece::fade_in(&mut piece, duration);
+                    piece
+                }
+            };
+            pieces[i] = Some(piece);
+        }
+
+        for (_, piece) in spare {
+            ChessPiece::fade_out(piece, duration);
+        }
+        self.pieces = pieces;
 
+        self.update_arrows();
+        self.update_highlights();
+        self.update_tablebase_label();
+        if self.analysis.is_some() {
+            self.start_analysis();
+        }
+        self.base_mut().emit_signal("captures_changed".into(), &[]);
+        self.base_mut().emit_signal("history_changed".into(), &[]);
+    }
+
+    fn receive_ai_move(&mut self) {
+        let Some(m) = self.ai_search.as_ref().and_then(|s| s.result()) else {
+            return;
+        };
+        self.ai_search = None;
+        if let Some(m) = m {
+            self.play_move(m);
+        }
+    }
+
+    fn add_square(&mut self, index: usize) {
         let mut sprite = Sprite2D::new_alloc();
-        sprite.set_texture(texture);
-        sprite.set_scale(Vector2::new(self.square_size, self.square_size));
-        sprite.set_modulate(if dark { self.dark_color } else { Color::WHITE });
-        sprite.set_position(Vector2::new(x, y));
         sprite.set_centered(false);
 
         self.squares.push(sprite.clone());
         self.base_mut().add_child(sprite.upcast());
+        self.style_square(index);
     }
 
-    pub fn place(&mut self, piece: &mut ChessPiece, position: Vector2) {
-        for (i, square) in self.squares.iter().enumerate() {
-            if square.get_rect().has_point(square.to_local(position)) {
-                if self.board.moves().contains(&Move {
-                    start: self.current_picked,
-                    end: i,
-                }) || i == self.current_picked
-                {
-                    // Drop piece on the square
-                    piece.base_mut().set_position(
-                        square.get_position()
-                            + Vector2::new(self.square_size / 2.0, self.square_size / 2.0),
-                    );
-                    piece.index = i;
-
-                    // Clear the last move's highlights
-                    let last_picked_color = if is_index_dark(self.last_picked) {
-                        self.dark_color
-                    } else {
-                        Color::WHITE
-                    };
-                    self.squares[self.last_picked].set_modulate(last_picked_color);
+    /// Gives the square at `index` its texture, or its colour if there's no texture for it, and
+    /// puts it in place.
+    fn style_square(&mut self, index: usize) {
+        let (color, texture) = if is_dark(index) {
+            (self.dark_color, self.dark_texture.clone())
+        } else {
+            (self.light_color, self.light_texture.clone())
+        };
+        let (modulate, texture) = match texture {
+            Some(texture) => (Color::WHITE, texture),
+            None => (color, load::<Texture2D>("res://art/White Square.png")),
+        };
+        let scale = Vector2::new(self.square_size, self.square_size) / texture.get_size();
+        let position = self.square_position(index);
 
-                    let last_placed_color = if is_index_dark(self.last_placed) {
-                        self.dark_color
-                    } else {
-                        Color::WHITE
-                    };
-                    self.squares[self.last_placed].set_modulate(last_placed_color);
-
-                    // Highlight the new placed square
-                    let mut picked_square = self.squares[self.current_picked].clone();
-                    let new_picked_color = picked_square
-                        .get_modulate()
-                        .lerp(Color::YELLOW.darkened(0.5), 0.5);
-                    picked_square.set_modulate(new_picked_color);
-
-                    // Make sure to clear any red before lerping
-                    let red = if is_index_dark(i) {
-                        self.squares[i].get_modulate() != self.dark_color
-                    } else {
-                        self.squares[i].get_modulate() != Color::WHITE
-                    };
-                    if red {
-                        if is_index_dark(i) {
-                            self.squares[i].set_modulate(self.dark_color);
-                        } else {
-                            self.squares[i].set_modulate(Color::WHITE);
-                        }
-                    }
+        let mut square = self.squares[index].clone();
+        square.set_texture(texture);
+        square.set_modulate(modulate);
+        square.set_scale(scale);
+        square.set_position(position);
+    }
 
-                    let mut placed_square = self.squares[i].clone();
-                    let new_color = placed_square.get_modulate().lerp(Color::YELLOW, 0.5);
-                    placed_square.set_modulate(new_color);
-                    self.last_placed = i;
-
-                    // Capture, if necessary, and update the board's state
-                    self.pieces[self.current_picked] = None;
-                    let picked_troop = self.board.troops[self.current_picked].unwrap();
-                    self.board.troops[self.current_picked] = None;
-                    if let Some(ref mut piece) = &mut self.pieces[i] {
-                        piece.queue_free();
-                    }
-                    self.pieces[i] = Some(piece.base().clone().cast());
-                    self.board.troops[i] = Some(picked_troop);
-                    if i != self.current_picked {
-                        self.board.turn = !self.board.turn;
-                    }
+    /// Copies `board_theme`'s settings over the board's own.
+    fn take_theme(&mut self) {
+        let Some(theme) = self.board_theme.clone() else {
+            return;
+        };
+        let theme = theme.bind();
+        self.light_color = theme.light_color;
+        self.dark_color = theme.dark_color;
+        self.light_texture = theme.light_texture.clone();
+        self.dark_texture = theme.dark_texture.clone();
+        self.piece_set = theme.piece_set.clone();
+        self.selected_color = theme.selected_color;
+        self.last_move_color = theme.last_move_color;
+        self.legal_target_color = theme.legal_target_color;
+        self.capture_target_color = theme.capture_target_color;
+        self.check_color = theme.check_color;
+        self.premove_color = theme.premove_color;
+        self.mark_color = theme.mark_color;
+        self.red_mark_color = theme.red_mark_color;
+        self.yellow_mark_color = theme.yellow_mark_color;
+        self.blue_mark_color = theme.blue_mark_color;
+        self.focus_color = theme.focus_color;
+        self.drag_target_color = theme.drag_target_color;
+        self.hint_color = theme.hint_color;
+    }
 
-                    // En passant capture check
-                    if self.board.en_passant_target == Some(i) {
-                        let piece_index = (i as i8
-                            + match self.board.turn {
-                                types::Color::White => -8,
-                                types::Color::Black => 8,
-                            }) as usize;
-                        let mut piece = self.pieces[piece_index].clone().unwrap();
-                        self.pieces[piece_index] = None;
-                        piece.queue_free();
-                    }
+    /// Looks for an SVG first, then a PNG. If `piece_set` has neither, reports it and returns
+    /// `None`, so a piece can keep the texture it has.
+    pub fn piece_texture(&self, troop: Troop) -> Option<Gd<Texture2D>> {
+        let svg = format!("{}/{}.svg", self.piece_set, troop);
+        let path = if ResourceLoader::singleton().exists(svg.clone().into()) {
+            svg
+        } else {
+            format!("{}/{}.png", self.piece_set, troop)
+        };
+        match try_load::<Texture2D>(path) {
+            Ok(texture) => Some(texture),
+            Err(e) => {
+                godot_error!("No texture for {} in {}: {}", troop, self.piece_set, e);
+                None
+            }
+        }
+    }
 
-                    // En passant target check
-                    if self.board.troops[i].unwrap().piece == Piece::Pawn
-                        && (i as i8 - self.current_picked as i8).abs() == 16
-                    {
-                        let target = (i as i8
-                            + match self.board.turn {
-                                types::Color::White => -8,
-                                types::Color::Black => 8,
-                            }) as usize;
-                        self.board.en_passant_target = Some(target);
-                    } else {
-                        self.board.en_passant_target = None;
-                    }
+    fn parent_control(&self) -> Option<Gd<Control>> {
+        self.base()
+            .get_parent()
+            .and_then(|parent| parent.try_cast::<Control>().ok())
+    }
 
-                    break;
-                } else {
-                    self.place(piece, self.squares[self.current_picked].get_position());
-                    break;
-                }
+    /// The largest whole square size that lets the board fit.
+    fn fitted_square_size(&self) -> f32 {
+        let size = match self.parent_control() {
+            Some(parent) => parent.get_size() - self.base().get_position(),
+            None => self.base().get_viewport_rect().size - self.base().get_position(),
+        };
+        (size.x.min(size.y) / 8.0).floor().max(1.0)
+    }
+
+    /// Pieces take up most of their square, whatever the size of their textures.
+    fn piece_scale(&self, piece: &Gd<ChessPiece>) -> Vector2 {
+        let Some(texture) = piece.get_texture() else {
+            return Vector2::ONE;
+        };
+        let texture_size = texture.get_size();
+        let scale = 0.9 * self.square_size / texture_size.x.max(texture_size.y);
+        Vector2::new(scale, scale)
+    }
+
+    /// Puts the squares, pieces and everything drawn over them where they belong for the current
+    /// `square_size` and orientation.
+    fn lay_out(&mut self) {
+        for i in 0..64 {
+            self.style_square(i);
+
+            let center = self.square_center(i);
+            if let Some(mut piece) = self.pieces[i].clone() {
+                piece.set_scale(self.piece_scale(&piece));
+                ChessPiece::slide(&mut piece, center, 0.0);
             }
         }
-        self.squares.iter_mut().enumerate().for_each(|(i, s)| {
-            let color = if is_index_dark(i) {
-                self.dark_color
+
+        let label_position = Vector2::new(0.0, 8.0 * self.square_size);
+        if let Some(label) = &mut self.tablebase_label {
+            label.set_position(label_position);
+        }
+        self.update_coordinates();
+        self.update_highlights();
+        self.update_arrows();
+    }
+
+    /// The column and row the square at `index` is shown in, from the top left.
+    fn screen_square(&self, index: usize) -> (usize, usize) {
+        let (column, row) = (index % 8, index / 8);
+        if self.flipped {
+            (7 - column, 7 - row)
+        } else {
+            (column, row)
+        }
+    }
+
+    /// Where the top left corner of the square at `index` goes.
+    fn square_position(&self, index: usize) -> Vector2 {
+        let (column, row) = self.screen_square(index);
+        Vector2::new(column as f32, row as f32) * self.square_size
+    }
+
+    fn add_coordinates(&mut self) {
+        for _ in 0..16 {
+            let label = Label::new_alloc();
+            self.coordinates.push(label.clone());
+            self.base_mut().add_child(label.upcast());
+        }
+        self.update_coordinates();
+    }
+
+    /// Puts file letters in the corners of the bottom row of squares and rank numbers in the
+    /// corners of the left column, in the opposite square colour.
+    fn update_coordinates(&mut self) {
+        let margin = self.square_size / 20.0;
+        let mut labels = self.coordinates.clone().into_iter();
+
+        for i in 0..64 {
+            let (column, row) = self.screen_square(i);
+            let (file, rank) = index_to_square(i);
+            let color = if is_dark(i) {
+                self.light_color
             } else {
-                Color::WHITE
+                self.dark_color
             };
-            if i != self.current_picked && i != self.last_placed && i != self.last_picked {
-                s.set_modulate(color);
+
+            for (shown, text, horizontal, vertical) in [
+                (
+                    row == 7,
+                    file.to_string(),
+                    HorizontalAlignment::RIGHT,
+                    VerticalAlignment::BOTTOM,
+                ),
+                (
+                    column == 0,
+                    rank.to_string(),
+                    HorizontalAlignment::LEFT,
+                    VerticalAlignment::TOP,
+                ),
+            ] {
+                if !shown {
+                    continue;
+                }
+                let Some(mut label) = labels.next() else {
+                    return;
+                };
+                label.set_text(text.into());
+                label.add_theme_font_size_override(
+                    "font_size".into(),
+                    (self.square_size / 5.0).round() as i32,
+                );
+                label.set_horizontal_alignment(horizontal);
+                label.set_vertical_alignment(vertical);
+                label.add_theme_color_override("font_color".into(), color);
+                label.set_position(self.square_position(i) + Vector2::new(margin, 0.0));
+                label.set_size(Vector2::new(
+                    self.square_size - 2.0 * margin,
+                    self.square_size,
+                ));
             }
-        });
+        }
     }
 
-    pub fn pick(&mut self, piece: &ChessPiece) {
-        let mut square = self.squares[piece.index].clone();
-        let new_color = square.get_modulate().lerp(Color::YELLOW, 0.5);
-        square.set_modulate(new_color);
-        self.base_mut()
-            .move_child(piece.base().clone().upcast(), -1);
-        self.last_picked = self.current_picked;
-        self.current_picked = piece.index;
-        for i in self.board.moves().iter_mut().filter_map(|m| {
-            if m.start == self.current_picked {
-                Some(m.end)
-            } else {
-                None
+    fn square_center(&self, index: usize) -> Vector2 {
+        self.squares[index].get_position()
+            + Vector2::new(self.square_size / 2.0, self.square_size / 2.0)
+    }
+
+    fn ai_color(&self) -> Option<types::Color> {
+        parse_color(&self.ai_color)
+    }
+
+    /// The side the player on this board is playing against, if it isn't the player too.
+    fn opponent_color(&self) -> Option<types::Color> {
+        self.ai_color().or(parse_color(&self.remote_color))
+    }
+
+    fn skill(&self) -> Skill {
+        if self.target_elo > 0 {
+            Skill::from_elo(self.target_elo)
+        } else {
+            Skill::from_level(self.skill_level)
+        }
+    }
+
+    /// Worked out from what the opponent has lost since the game started, so en passant and
+    /// pieces that were promoted are counted, and nothing counts as captured in a position set up
+    /// with `set_fen`.
+    pub fn captured_by(&self, color: types::Color) -> Vec<Troop> {
+        self.board.missing_pieces(!color, &self.first_board())
+    }
+
+    /// The position the game started from.
+    pub fn first_board(&self) -> Board {
+        self.history.first().map_or(self.board, |(board, _)| *board)
+    }
+
+    pub fn san_moves(&self) -> Vec<String> {
+        self.history.iter().map(|(board, m)| board.san(m)).collect()
+    }
+
+    pub fn viewed_ply(&self) -> usize {
+        self.viewed_ply.unwrap_or(self.history.len())
+    }
+
+    /// The position on screen, which is the current one unless an earlier one is being viewed.
+    fn shown_board(&self) -> Board {
+        self.viewed_ply
+            .map_or(self.board, |ply| self.history[ply].0)
+    }
+
+    fn shown_last_move(&self) -> Option<Move> {
+        match self.viewed_ply {
+            Some(0) => None,
+            Some(ply) => Some(self.history[ply - 1].1),
+            None => self.last_move,
+        }
+    }
+
+    /// How much white's lead in material has changed since the game started.
+    pub fn material_difference(&self) -> i32 {
+        let lead = |board: Board| {
+            board.material(types::Color::White) - board.material(types::Color::Black)
+        };
+        lead(self.board) - lead(self.first_board())
+    }
+
+    /// The square `piece` is on. It's looked up here rather than kept on the piece, so the board
+    /// never has to bind a piece that may be in the middle of handling its own input.
+    pub fn square_of(&self, piece: &Gd<ChessPiece>) -> Option<usize> {
+        self.pieces.iter().position(|p| p.as_ref() == Some(piece))
+    }
+
+    /// Whether the player may pick up the piece at `index` right now.
+    pub fn can_pick(&self, index: usize) -> bool {
+        let Some(troop) = self.board.troops[index] else {
+            return false;
+        };
+        let opponent = self.opponent_color();
+        let premove = self.allow_premoves && opponent == Some(self.board.turn);
+
+        self.result.is_none()
+            && self.viewed_ply.is_none()
+            && self.pending_promotion.is_none()
+            && opponent != Some(troop.color)
+            && (troop.color == self.board.turn || premove)
+    }
+
+    /// Follows a dragged piece, highlighting the square under it.
+    pub fn drag_to(&mut self, position: Vector2) {
+        let target = self.square_at(position);
+        if target != self.drag_target {
+            self.drag_target = target;
+            self.update_highlights();
+        }
+    }
+
+    fn end_drag(&mut self) {
+        self.dragged = None;
+        if let Some(mut ghost) = self.ghost.take() {
+            ghost.queue_free();
+        }
+        if self.drag_target.take().is_some() {
+            self.update_highlights();
+        }
+    }
+
+    /// Makes the dragged piece let go of the mouse, for when it's being removed or the position is
+    /// replaced under it.
+    fn cancel_drag(&mut self) {
+        let Some(mut piece) = self.dragged.clone() else {
+            return;
+        };
+        piece.bind_mut().let_go();
+        self.end_drag();
+        self.unpick();
+    }
+
+    /// Drops the piece dragged from `start` at `position`.
+    pub fn place(&mut self, start: usize, position: Vector2) {
+        self.end_drag();
+        let Some(i) = self.square_at(position) else {
+            self.snap_back(start, None);
+            self.unpick();
+            return;
+        };
+
+        if i == start {
+            // Dropping a piece back on its own square selects it for click-to-move, unless it
+            // was already selected
+            let center = self.square_center(i);
+            if let Some(piece) = &mut self.pieces[i] {
+                piece.set_position(center);
             }
-        }) {
-            let mut square = self.squares[i].clone();
-            let dark = is_index_dark(i);
-            let yellow = if dark {
-                square.get_modulate() != self.dark_color
+            if self.selected == Some(i) {
+                self.unpick();
             } else {
-                square.get_modulate() != Color::WHITE
+                self.selected = Some(i);
+            }
+        } else if !self.move_picked(start, i) {
+            self.snap_back(start, Some(i));
+            self.unpick();
+            self.play_sound(self.illegal_sound.clone());
+        }
+    }
+
+    /// Sends the piece dropped from `start` back there. `end` is where it was dropped, unless
+    /// that's off the board.
+    fn snap_back(&mut self, start: usize, end: Option<usize>) {
+        let Some(mut piece) = self.pieces[start].clone() else {
+            return;
+        };
+        let duration = self.animation_duration;
+        let shake = if end.is_some() && self.shake_illegal_drops {
+            self.square_size / 12.0
+        } else {
+            0.0
+        };
+        ChessPiece::snap_back(&mut piece, self.square_center(start), duration, shake);
+        if end.is_some() {
+            ChessPiece::flash(&mut piece, self.illegal_drop_color, duration * 3.0);
+        }
+
+        let troop = self.board.troops[start].map(|t| t.to_string());
+        self.base_mut().emit_signal(
+            "piece_snapped_back".into(),
+            &[
+                GString::from(troop.unwrap_or_default()).to_variant(),
+                GString::from(square_name(start)).to_variant(),
+                GString::from(end.map(square_name).unwrap_or_default()).to_variant(),
+            ],
+        );
+    }
+
+    /// Moves the selected piece to the clicked square, or cancels the selection if it can't go
+    /// there.
+    fn click(&mut self, position: Vector2) {
+        let Some(start) = self.selected else {
+            return;
+        };
+        let Some(i) = self.square_at(position) else {
+            self.unpick();
+            return;
+        };
+        if !self.move_picked(start, i) {
+            self.unpick();
+            self.play_sound(self.illegal_sound.clone());
+        }
+    }
+
+    /// Moves the keyboard cursor by `columns` and `rows` as seen on screen, or shows it if it's
+    /// hidden.
+    fn move_cursor(&mut self, columns: i32, rows: i32) {
+        let cursor = match self.cursor {
+            Some(cursor) => {
+                let (column, row) = self.screen_square(cursor);
+                let column = (column as i32 + columns).clamp(0, 7) as usize;
+                let row = (row as i32 + rows).clamp(0, 7) as usize;
+                // Flipping is its own inverse
+                let (column, row) = self.screen_square(row * 8 + column);
+                row * 8 + column
+            }
+            None => self
+                .selected
+                .or(self.last_move.map(|m| m.end))
+                .unwrap_or(if self.flipped { 7 } else { 56 }),
+        };
+        self.cursor = Some(cursor);
+        self.update_highlights();
+        self.announce(self.shown_board().describe_square(cursor));
+    }
+
+    /// Like clicking the square under the keyboard cursor.
+    fn press_cursor(&mut self) {
+        let Some(cursor) = self.cursor else {
+            return;
+        };
+        if let Some(start) = self.selected {
+            if start == cursor {
+                self.unpick();
+                self.announce("Selection cancelled".into());
+                return;
+            }
+            if self.move_picked(start, cursor) {
+                return;
+            }
+        }
+
+        if self.can_pick(cursor) {
+            self.clear_annotations();
+            self.selected = Some(cursor);
+            self.current_picked = Some(cursor);
+            self.update_highlights();
+            self.announce(format!("{} selected", self.board.describe_square(cursor)));
+        } else if self.selected.is_some() {
+            self.unpick();
+            self.announce("Can't move there".into());
+            self.play_sound(self.illegal_sound.clone());
+        } else {
+            self.announce(self.shown_board().describe_square(cursor));
+        }
+    }
+
+    /// Plays `sound`, cutting off whatever was playing.
+    fn play_sound(&mut self, sound: Option<Gd<AudioStream>>) {
+        let (Some(sound), Some(sound_player)) = (sound, &mut self.sound_player) else {
+            return;
+        };
+        sound_player.set_stream(sound);
+        sound_player.play();
+    }
+
+    fn announce(&mut self, text: String) {
+        self.base_mut()
+            .emit_signal("announcement".into(), &[GString::from(text).to_variant()]);
+    }
+
+    fn unpick(&mut self) {
+        self.selected = None;
+        self.current_picked = None;
+        self.update_highlights();
+    }
+
+    fn square_at(&self, position: Vector2) -> Option<usize> {
+        self.squares
+            .iter()
+            .position(|s| s.get_rect().has_point(s.to_local(position)))
+    }
+
+    /// Plays the move from `start` to `end`, if it's legal.
+    fn move_picked(&mut self, start: usize, end: usize) -> bool {
+        if self.board.troops[start].is_some_and(|t| t.color != self.board.turn) {
+            return self.queue_premove(start, end);
+        }
+
+        // Promotions go to a queen unless something asks the player
+        let Some(m) = self.board.legal_moves().into_iter().find(|m| {
+            m.start == start && m.end == end && matches!(m.promotion, None | Some(Piece::Queen))
+        }) else {
+            return false;
+        };
+        let ask_promotion = !self
+            .base()
+            .get_signal_connection_list("promotion_requested".into())
+            .is_empty();
+
+        if m.promotion.is_some() && ask_promotion {
+            let center = self.square_center(end);
+            if let Some(piece) = &mut self.pieces[m.start] {
+                ChessPiece::slide(piece, center, self.animation_duration);
+            }
+            self.unpick();
+            self.pending_promotion = Some(Move {
+                promotion: None,
+                ..m
+            });
+            // Deferred, so a handler calling `promote` straight away finishes the move after the
+            // drop that asked for it rather than in the middle of it
+            self.base_mut().call_deferred(
+                "emit_signal".into(),
+                &[
+                    StringName::from("promotion_requested").to_variant(),
+                    GString::from(square_name(end)).to_variant(),
+                ],
+            );
+        } else {
+            self.play_move(m);
+        }
+        true
+    }
+
+    /// The board as it would be after the queued premoves, ignoring the opponent's replies.
+    fn premove_board(&self) -> Board {
+        let mut board = self.board;
+        for m in &self.premoves {
+            board.turn = !self.board.turn;
+            board.make_move(*m);
+        }
+        board.turn = !self.board.turn;
+        board
+    }
+
+    /// Where the piece on `start` will be once the queued premoves are played, so one piece can be
+    /// premoved more than once.
+    fn premove_square(&self, start: usize) -> usize {
+        let mut board = self.board;
+        let mut square = start;
+        for m in &self.premoves {
+            board.turn = !self.board.turn;
+            if m.start == square {
+                square = m.end;
+            } else if let Some(rook_move) =
+                board.castling_rook_move(m).filter(|r| r.start == square)
+            {
+                square = rook_move.end;
+            }
+            board.make_move(*m);
+        }
+        square
+    }
+
+    /// Queues the move from where the piece on `start` will be to `end`. `start` is the square the
+    /// piece is shown on.
+    fn queue_premove(&mut self, start: usize, end: usize) -> bool {
+        let board = self.premove_board();
+        let from = self.premove_square(start);
+        if !board.premove_targets(from).contains(&end) {
+            return false;
+        }
+
+        let promotion = board.troops[from]
+            .filter(|t| t.piece == Piece::Pawn && (end / 8 == 0 || end / 8 == 7))
+            .map(|_| Piece::Queen);
+        self.premoves.push(Move {
+            start: from,
+            end,
+            promotion,
+        });
+
+        // The piece stays put until the premove is played
+        let center = self.square_center(start);
+        if let Some(piece) = &mut self.pieces[start] {
+            ChessPiece::slide(piece, center, self.animation_duration);
+        }
+        self.unpick();
+        true
+    }
+
+    /// Plays the first queued premove if it's the player's turn, or drops them all if it isn't
+    /// legal.
+    fn play_premove(&mut self) {
+        if self.premoves.is_empty() || self.opponent_color() == Some(self.board.turn) {
+            return;
+        }
+
+        let premove = self.premoves[0];
+        if self.board.legal_moves().contains(&premove) {
+            self.premoves.remove(0);
+            self.play_move(premove);
+        } else {
+            self.cancel_premoves();
+        }
+    }
+
+    /// Updates the board and moves the piece nodes to match.
+    fn play_move(&mut self, m: Move) {
+        if self.viewed_ply.is_some() {
+            let shown = self.shown_board().troops;
+            self.viewed_ply = None;
+            self.refresh(shown);
+        }
+
+        let captured_square = self.board.capture_square(&m);
+        let rook_move = self.board.castling_rook_move(&m);
+        let color = self.board.turn;
+        let san = self.board.san(&m);
+        let description = self.board.describe_move(&m);
+        self.history.push((self.board, m));
+        self.selected = None;
+        self.current_picked = None;
+        let captured = self.board.make_move(m);
+        self.positions.push(self.board.position_key());
+        if let Some(clock) = &mut self.clock {
+            clock.press();
+        }
+
+        // Capture, if necessary
+        let duration = self.animation_duration;
+        if let Some(captured_square) = captured_square {
+            if let Some(captured) = self.pieces[captured_square].take() {
+                if self.dragged.as_ref() == Some(&captured) {
+                    self.cancel_drag();
+                }
+                ChessPiece::fade_out(captured, duration);
+            }
+        }
+
+        let mut moved = self.pieces[m.start].take().unwrap();
+        self.base_mut().move_child(moved.clone().upcast(), -1);
+        ChessPiece::slide(&mut moved, self.square_center(m.end), duration);
+        if let Some(texture) = m
+            .promotion
+            .and_then(|piece| self.piece_texture(Troop { color, piece }))
+        {
+            moved.set_texture(texture);
+        }
+        self.pieces[m.end] = Some(moved);
+
+        if let Some(rook_move) = rook_move {
+            let mut rook = self.pieces[rook_move.start].take().unwrap();
+            ChessPiece::slide(&mut rook, self.square_center(rook_move.end), duration);
+            self.pieces[rook_move.end] = Some(rook);
+        }
+
+        self.last_move = Some(m);
+        self.hint = None;
+        self.hint_search = None;
+        self.update_arrows();
+        self.update_highlights();
+        self.update_tablebase_label();
+        if self.analysis.is_some() {
+            self.start_analysis();
+        }
+
+        let fen = self.board.to_fen();
+        self.base_mut().emit_signal(
+            "move_made".into(),
+            &[
+                GString::from(m.to_uci()).to_variant(),
+                GString::from(san).to_variant(),
+                GString::from(fen).to_variant(),
+            ],
+        );
+        self.base_mut().emit_signal("history_changed".into(), &[]);
+        self.announce(format!("{}: {}", color, description));
+        if let (Some(troop), Some(square)) = (captured, captured_square) {
+            self.base_mut().emit_signal(
+                "piece_captured".into(),
+                &[
+                    GString::from(troop.to_string()).to_variant(),
+                    GString::from(square_name(square)).to_variant(),
+                ],
+            );
+        }
+        if captured.is_some() || m.promotion.is_some() {
+            self.base_mut().emit_signal("captures_changed".into(), &[]);
+        }
+        let turn = self.board.turn;
+        let check = self.board.is_in_check(turn);
+        let sound = [
+            (check, &self.check_sound),
+            (m.promotion.is_some(), &self.promotion_sound),
+            (rook_move.is_some(), &self.castle_sound),
+            (captured.is_some(), &self.capture_sound),
+        ]
+        .into_iter()
+        .find_map(|(happened, sound)| sound.clone().filter(|_| happened))
+        .or(self.move_sound.clone());
+        self.play_sound(sound);
+        if check {
+            let king = self.board.king_square(turn).unwrap();
+            self.base_mut().emit_signal(
+                "check".into(),
+                &[GString::from(square_name(king)).to_variant()],
+            );
+        }
+        self.base_mut().emit_signal(
+            "turn_changed".into(),
+            &[GString::from(turn.to_string()).to_variant()],
+        );
+
+        let key = self.board.position_key();
+        let repetitions = self.positions.iter().filter(|p| **p == key).count();
+        if let Some((result, termination)) = self.board.outcome() {
+            self.end_game(result, termination);
+        } else if repetitions >= 3 {
+            self.end_game(GameResult::Draw, Termination::ThreefoldRepetition);
+        } else {
+            self.start_ai_if_needed();
+            self.play_premove();
+        }
+    }
+
+    fn start_ai_if_needed(&mut self) {
+        let turn = self.board.turn;
+        if self.result.is_some()
+            || self.ai_color() != Some(turn)
+            || self.board.legal_moves().is_empty()
+        {
+            return;
+        }
+
+        let board = self.board;
+        let skill = self.skill();
+        let tablebase = self.tablebase.clone();
+        let time = self.clock.as_ref().map(|clock| TimeControl {
+            remaining: clock.remaining(turn),
+            increment: clock.increment(turn),
+            moves_to_go: clock.moves_to_go(turn),
+        });
+
+        self.ai_search = Some(MoveSearch::start(move |stop| {
+            skill.choose_move(&board, tablebase.as_deref(), time, Some(stop))
+        }));
+    }
+
+    fn update_tablebase_label(&mut self) {
+        let Some(label) = &mut self.tablebase_label else {
+            return;
+        };
+        let text = match self.tablebase.as_ref().and_then(|t| t.probe(&self.board)) {
+            Some(probe) => probe.describe(self.board.turn),
+            None => String::new(),
+        };
+        label.set_text(text.into());
+    }
+
+    /// Redraws the highlights for the current state of the game, from the bottom layer up.
+    fn update_highlights(&mut self) {
+        let mut highlights = Vec::new();
+
+        if let Some(m) = self.shown_last_move() {
+            highlights.push((m.start, self.last_move_color, HighlightShape::Fill));
+            highlights.push((m.end, self.last_move_color, HighlightShape::Fill));
+        }
+
+        if let Some(hint) = self.hint.filter(|_| self.hint_highlights_squares) {
+            let color = Color {
+                a: self.hint_color.a * 0.5,
+                ..self.hint_color
             };
-            if yellow {
-                if dark {
-                    square.set_modulate(self.dark_color);
-                } else {
-                    square.set_modulate(Color::WHITE);
+            highlights.push((hint.start, color, HighlightShape::Fill));
+            highlights.push((hint.end, color, HighlightShape::Fill));
+        }
+
+        let shown = self.shown_board();
+        if let Some(king) = shown
+            .king_square(shown.turn)
+            .filter(|_| shown.is_in_check(shown.turn))
+        {
+            highlights.push((king, self.check_color, HighlightShape::Fill));
+        }
+
+        for (square, brush) in &self.annotations.squares {
+            highlights.push((*square, self.brush_color(*brush), HighlightShape::Fill));
+        }
+
+        for m in self.premoves.iter().filter(|_| self.viewed_ply.is_none()) {
+            highlights.push((m.start, self.premove_color, HighlightShape::Fill));
+            highlights.push((m.end, self.premove_color, HighlightShape::Fill));
+        }
+
+        if let Some(start) = self.current_picked {
+            highlights.push((start, self.selected_color, HighlightShape::Fill));
+            let dot = HighlightShape::Dot {
+                radius: self.legal_target_size * self.square_size / 2.0,
+            };
+            let ring = HighlightShape::Ring {
+                width: self.capture_target_width * self.square_size,
+            };
+            if self.board.troops[start].is_some_and(|t| t.color == self.board.turn) {
+                for m in self.board.legal_moves().iter().filter(|m| m.start == start) {
+                    if self.board.capture_square(m).is_some() {
+                        highlights.push((m.end, self.capture_target_color, ring));
+                    } else {
+                        highlights.push((m.end, self.legal_target_color, dot));
+                    }
+                }
+            } else {
+                let board = self.premove_board();
+                for end in board.premove_targets(self.premove_square(start)) {
+                    if board.troops[end].is_some() {
+                        highlights.push((end, self.capture_target_color, ring));
+                    } else {
+                        highlights.push((end, self.legal_target_color, dot));
+                    }
                 }
             }
-            let new_color = square.get_modulate().lerp(Color::RED, 0.5);
-            square.set_modulate(new_color);
+        }
+        if let Some(target) = self.drag_target.filter(|i| Some(*i) != self.current_picked) {
+            highlights.push((target, self.drag_target_color, HighlightShape::Fill));
+        }
+
+        if let Some(cursor) = self.cursor {
+            let ring = HighlightShape::Ring {
+                width: self.square_size / 15.0,
+            };
+            highlights.push((cursor, self.focus_color, ring));
+        }
+        // Each promotion shows up four times
+        highlights.dedup();
+
+        let size = Vector2::new(self.square_size, self.square_size);
+        let highlights = highlights
+            .into_iter()
+            .map(|(i, color, shape)| Highlight {
+                rect: Rect2::new(self.square_position(i), size),
+                color,
+                shape,
+            })
+            .collect();
+        if let Some(highlight_layer) = &mut self.highlight_layer {
+            highlight_layer.bind_mut().set_highlights(highlights);
         }
     }
+
+    /// Picks up the piece on `index` to be dragged.
+    pub fn pick(&mut self, index: usize) {
+        self.clear_annotations();
+        self.end_drag();
+        let Some(mut piece) = self.pieces[index].clone() else {
+            return;
+        };
+        // It follows the mouse from now on
+        ChessPiece::stop(&mut piece);
+        self.dragged = Some(piece.clone());
+        if self.ghost_opacity > 0.0 {
+            let mut ghost = Sprite2D::new_alloc();
+            if let Some(texture) = piece.get_texture() {
+                ghost.set_texture(texture);
+            }
+            ghost.set_scale(piece.get_scale());
+            ghost.set_position(self.square_center(index));
+            ghost.set_modulate(Color::from_rgba(1.0, 1.0, 1.0, self.ghost_opacity));
+            self.base_mut().add_child(ghost.clone().upcast());
+            self.ghost = Some(ghost);
+        }
+        self.base_mut().move_child(piece.upcast(), -1);
+        self.current_picked = Some(index);
+        // Picking the selected piece up again keeps it selected, so dropping it deselects it
+        self.selected = self.selected.filter(|i| *i == index);
+        self.update_highlights();
+    }
 }
 
-fn is_index_dark(index: usize) -> bool {
-    let rank = index / 8;
-    let file = index % 8;
-    rank % 2 != file % 2
+fn parse_color(color: &GString) -> Option<types::Color> {
+    match color.to_string().to_lowercase().as_str() {
+        "white" => Some(types::Color::White),
+        "black" => Some(types::Color::Black),
+        _ => None,
+    }
 }
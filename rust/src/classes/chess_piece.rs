@@ -1,17 +1,23 @@
 use crate::classes::ChessBoard2D;
 use godot::{
     engine::{
-        display_server::CursorShape, global::MouseButton, Area2D, CollisionShape2D, DisplayServer,
-        ISprite2D, InputEvent, InputEventMouseButton, RectangleShape2D, Sprite2D,
+        display_server::CursorShape,
+        global::MouseButton,
+        tween::{EaseType, TransitionType},
+        Area2D, CollisionShape2D, DisplayServer, ISprite2D, InputEvent, InputEventMouseButton,
+        RectangleShape2D, Sprite2D, Tween,
     },
     prelude::*,
 };
 
+/// The highest z index allowed, `RenderingServer::CANVAS_ITEM_Z_MAX`, so a dragged piece is
+/// drawn over everything else on its canvas layer.
+const DRAG_Z_INDEX: i32 = 4096;
+
 #[derive(GodotClass)]
 #[class(base = Sprite2D)]
 pub struct ChessPiece {
     is_held: bool,
-    pub index: usize,
     hovered: bool,
 
     base: Base<Sprite2D>,
@@ -22,15 +28,15 @@ impl ISprite2D for ChessPiece {
     fn init(base: Base<Sprite2D>) -> Self {
         Self {
             is_held: false,
-            index: 0,
             hovered: false,
             base,
         }
     }
 
     fn ready(&mut self) {
+        // Scales along with the piece, since it's a child
         let mut shape = RectangleShape2D::new_gd();
-        shape.set_size(Vector2::new(45.0, 45.0));
+        shape.set_size(self.base().get_rect().size);
         let mut collision_shape = CollisionShape2D::new_alloc();
         collision_shape.set_shape(shape.upcast());
         let mut area = Area2D::new_alloc();
@@ -57,40 +63,57 @@ impl ISprite2D for ChessPiece {
             {
                 let mut board_2d = self.base().get_parent().unwrap().cast::<ChessBoard2D>();
                 let mut board_2d = board_2d.bind_mut();
-                if event.is_pressed()
-                    && board_2d.board.turn == board_2d.board.troops[self.index].unwrap().color
-                {
+                let Some(index) = board_2d.square_of(&self.base().clone().cast()) else {
+                    return;
+                };
+                if event.is_pressed() && board_2d.can_pick(index) {
                     self.is_held = true;
-                    board_2d.pick(self);
+                    self.base_mut().set_z_index(DRAG_Z_INDEX);
+                    board_2d.pick(index);
+                    // Keep the board from treating this as a click-to-move target
+                    self.base().get_viewport().unwrap().set_input_as_handled();
                 }
                 if event.is_released() && self.is_held {
-                    self.is_held = false;
-                    board_2d.place(self, event.get_position());
+                    self.let_go();
+                    board_2d.place(index, event.get_position());
                 }
             }
         }
     }
 
     fn process(&mut self, _delta: f64) {
+        let mut server = DisplayServer::singleton();
         if self.is_held {
             let mouse_position = self.base().get_viewport().unwrap().get_mouse_position();
-            self.base_mut().set_position(mouse_position);
-        }
-        if self.hovered {
-            let mut server = DisplayServer::singleton();
-            server.cursor_set_shape(CursorShape::POINTING_HAND);
+            // The board needn't be at the origin, so this isn't the piece's local position
+            self.base_mut().set_global_position(mouse_position);
+            let mut board = self.base().get_parent().unwrap().cast::<ChessBoard2D>();
+            board.bind_mut().drag_to(mouse_position);
+            // A closed hand on most platforms
+            server.cursor_set_shape(CursorShape::DRAG);
+        } else if self.hovered {
+            // An open hand on most platforms
+            server.cursor_set_shape(CursorShape::CAN_DROP);
         }
     }
 }
 
+impl ChessPiece {
+    /// Stops following the mouse.
+    pub fn let_go(&mut self) {
+        self.is_held = false;
+        self.base_mut().set_z_index(0);
+    }
+}
+
 #[godot_api]
 impl ChessPiece {
     #[func]
     fn on_mouse_entered(&mut self) {
         let board = self.base().get_parent().unwrap().cast::<ChessBoard2D>();
-        let board = &board.bind().board;
-        if board.turn == board.troops[self.index].unwrap().color {
-            self.hovered = true;
+        let board = board.bind();
+        if let Some(index) = board.square_of(&self.base().clone().cast()) {
+            self.hovered = board.can_pick(index);
         }
     }
 
@@ -99,3 +122,124 @@ impl ChessPiece {
         self.hovered = false;
     }
 }
+
+/// Metadata holding the tween moving a piece, so a new move can stop the last one. It's kept on
+/// the node rather than in a field for the same reason as below.
+const MOTION_META: &str = "motion_tween";
+
+/// These take the piece's `Gd` rather than `&mut self`, since the board animates pieces that may
+/// already be bound, like one being dropped.
+impl ChessPiece {
+    /// Stops `piece` wherever its current slide has got to.
+    pub fn stop(piece: &mut Gd<Self>) {
+        let meta = StringName::from(MOTION_META);
+        if !piece.has_meta(meta.clone()) {
+            return;
+        }
+        if let Ok(mut tween) = piece.get_meta(meta.clone()).try_to::<Gd<Tween>>() {
+            tween.kill();
+        }
+        piece.remove_meta(meta);
+    }
+
+    /// Slides `piece` to `position` over `duration` seconds.
+    pub fn slide(piece: &mut Gd<Self>, position: Vector2, duration: f64) {
+        Self::stop(piece);
+        if duration <= 0.0 {
+            piece.set_position(position);
+            return;
+        }
+
+        let mut tween = piece.create_tween().unwrap();
+        tween.set_ease(EaseType::OUT);
+        tween.set_trans(TransitionType::CUBIC);
+        tween.tween_property(
+            piece.clone().upcast(),
+            "position".into(),
+            position.to_variant(),
+            duration,
+        );
+        piece.set_meta(MOTION_META.into(), tween.to_variant());
+    }
+
+    /// Fades `piece` out, then frees it. It stops taking input straight away.
+    pub fn fade_out(mut piece: Gd<Self>, duration: f64) {
+        piece.set_process_input(false);
+        if duration <= 0.0 {
+            piece.queue_free();
+            return;
+        }
+
+        let mut tween = piece.create_tween().unwrap();
+        tween.tween_property(
+            piece.clone().upcast(),
+            "modulate:a".into(),
+            0.0.to_variant(),
+            duration,
+        );
+        tween.tween_callback(piece.callable("queue_free"));
+    }
+
+    /// Like `slide`, then shakes `piece` from side to side by up to `shake` pixels.
+    pub fn snap_back(piece: &mut Gd<Self>, position: Vector2, duration: f64, shake: f32) {
+        Self::stop(piece);
+        if duration <= 0.0 {
+            piece.set_position(position);
+            return;
+        }
+
+        let mut tween = piece.create_tween().unwrap();
+        tween.set_ease(EaseType::OUT);
+        tween.set_trans(TransitionType::CUBIC);
+        tween.tween_property(
+            piece.clone().upcast(),
+            "position".into(),
+            position.to_variant(),
+            duration,
+        );
+        if shake > 0.0 {
+            for offset in [1.0, -1.0, 0.5, -0.5, 0.0] {
+                tween.tween_property(
+                    piece.clone().upcast(),
+                    "position".into(),
+                    (position + Vector2::new(offset * shake, 0.0)).to_variant(),
+                    duration / 4.0,
+                );
+            }
+        }
+        piece.set_meta(MOTION_META.into(), tween.to_variant());
+    }
+
+    /// Tints `piece` with `color`, fading back to normal over `duration` seconds.
+    pub fn flash(piece: &mut Gd<Self>, color: Color, duration: f64) {
+        if duration <= 0.0 || color == Color::WHITE {
+            return;
+        }
+
+        piece.set_modulate(color);
+        let mut tween = piece.create_tween().unwrap();
+        tween.tween_property(
+            piece.clone().upcast(),
+            "modulate".into(),
+            Color::WHITE.to_variant(),
+            duration,
+        );
+    }
+
+    pub fn fade_in(piece: &mut Gd<Self>, duration: f64) {
+        if duration <= 0.0 {
+            return;
+        }
+
+        let mut modulate = piece.get_modulate();
+        modulate.a = 0.0;
+        piece.set_modulate(modulate);
+        let mut tween = piece.create_tween().unwrap();
+        tween.tween_property(
+            piece.clone().upcast(),
+            "modulate:a".into(),
+            1.0.to_variant(),
+            duration,
+        );
+    }
+}
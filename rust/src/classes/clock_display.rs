@@ -0,0 +1,75 @@
+use godot::{
+    engine::{ILabel, Label},
+    prelude::*,
+};
+
+/// Shows one side's remaining time, driven by a `ChessBoard2D`'s clock signals.
+#[derive(GodotClass)]
+#[class(base = Label)]
+pub struct ClockDisplay {
+    #[export]
+    board: NodePath,
+    #[export]
+    /// "white" or "black".
+    color: GString,
+    base: Base<Label>,
+}
+
+#[godot_api]
+impl ILabel for ClockDisplay {
+    fn init(base: Base<Label>) -> Self {
+        Self {
+            board: NodePath::default(),
+            color: "white".into(),
+            base,
+        }
+    }
+
+    fn ready(&mut self) {
+        let mut board = self.base().get_node_as::<Node>(self.board.clone());
+        board.connect(
+            "clock_updated".into(),
+            self.base().callable("on_clock_updated"),
+        );
+        board.connect("flagged".into(), self.base().callable("on_flagged"));
+    }
+}
+
+#[godot_api]
+impl ClockDisplay {
+    fn is_white(&self) -> bool {
+        self.color.to_string().to_lowercase() == "white"
+    }
+
+    #[func]
+    fn on_clock_updated(&mut self, white_seconds: f64, black_seconds: f64) {
+        let seconds = if self.is_white() {
+            white_seconds
+        } else {
+            black_seconds
+        };
+        self.base_mut().set_text(format_time(seconds).into());
+    }
+
+    #[func]
+    fn on_flagged(&mut self, color: GString, _result: GString) {
+        if (color.to_string() == "White") == self.is_white() {
+            self.base_mut().set_modulate(Color::RED);
+        }
+    }
+}
+
+/// Tenths of a second are only shown in the last ten seconds.
+fn format_time(seconds: f64) -> String {
+    if seconds < 10.0 {
+        return format!("0:{:04.1}", seconds);
+    }
+
+    let seconds = seconds as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
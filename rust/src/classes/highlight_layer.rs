@@ -0,0 +1,68 @@
+use godot::prelude::*;
+use std::f32::consts::TAU;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum HighlightShape {
+    /// Fills the whole square.
+    Fill,
+    /// A dot in the middle of the square.
+    Dot { radius: f32 },
+    /// A ring just inside the square's edges.
+    Ring { width: f32 },
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Highlight {
+    pub rect: Rect2,
+    pub color: Color,
+    pub shape: HighlightShape,
+}
+
+/// Draws translucent highlights over the squares, in order, so later ones show on top of earlier
+/// ones on the same square.
+#[derive(GodotClass)]
+#[class(base = Node2D)]
+pub struct HighlightLayer {
+    highlights: Vec<Highlight>,
+    base: Base<Node2D>,
+}
+
+#[godot_api]
+impl INode2D for HighlightLayer {
+    fn init(base: Base<Node2D>) -> Self {
+        Self {
+            highlights: Vec::new(),
+            base,
+        }
+    }
+
+    fn draw(&mut self) {
+        for highlight in self.highlights.clone() {
+            let center = highlight.rect.center();
+            match highlight.shape {
+                HighlightShape::Fill => {
+                    self.base_mut().draw_rect(highlight.rect, highlight.color);
+                }
+                HighlightShape::Dot { radius } => {
+                    self.base_mut().draw_circle(center, radius, highlight.color);
+                }
+                HighlightShape::Ring { width } => {
+                    let radius =
+                        highlight.rect.size.x.min(highlight.rect.size.y) / 2.0 - width / 2.0;
+                    self.base_mut()
+                        .draw_arc_ex(center, radius, 0.0, TAU, 64, highlight.color)
+                        .width(width)
+                        .antialiased(true)
+                        .done();
+                }
+            }
+        }
+    }
+}
+
+impl HighlightLayer {
+    pub fn set_highlights(&mut self, highlights: Vec<Highlight>) {
+        self.highlights = highlights;
+        self.base_mut().queue_redraw();
+    }
+}
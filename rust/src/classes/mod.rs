@@ -1,4 +1,18 @@
+pub mod arrow_layer;
+pub use arrow_layer::*;
+pub mod board_theme;
+pub use board_theme::*;
+pub mod captured_pieces;
+pub use captured_pieces::*;
 pub mod chess_board;
 pub use chess_board::*;
 pub mod chess_piece;
 pub use chess_piece::*;
+pub mod clock_display;
+pub use clock_display::*;
+pub mod highlight_layer;
+pub use highlight_layer::*;
+pub mod move_entry;
+pub use move_entry::*;
+pub mod move_list;
+pub use move_list::*;
@@ -0,0 +1,48 @@
+use crate::classes::ChessBoard2D;
+use godot::{
+    engine::{ILineEdit, LineEdit},
+    prelude::*,
+};
+
+/// A text field for playing moves on a `ChessBoard2D` by typing them, like "Nf3" or "g1f3".
+#[derive(GodotClass)]
+#[class(base = LineEdit)]
+pub struct MoveEntry {
+    #[export]
+    board: NodePath,
+    base: Base<LineEdit>,
+}
+
+#[godot_api]
+impl ILineEdit for MoveEntry {
+    fn init(base: Base<LineEdit>) -> Self {
+        Self {
+            board: NodePath::default(),
+            base,
+        }
+    }
+
+    fn ready(&mut self) {
+        if self.base().get_placeholder().is_empty() {
+            self.base_mut()
+                .set_placeholder("Type a move, like Nf3".into());
+        }
+        let callable = self.base().callable("on_text_submitted");
+        self.base_mut().connect("text_submitted".into(), callable);
+    }
+}
+
+#[godot_api]
+impl MoveEntry {
+    #[func]
+    fn on_text_submitted(&mut self, text: GString) {
+        let mut board = self.base().get_node_as::<ChessBoard2D>(self.board.clone());
+        let played = board.bind_mut().enter_move(text);
+        if played {
+            self.base_mut().clear();
+        } else {
+            // Leave it there to be fixed, or typed over
+            self.base_mut().select_all();
+        }
+    }
+}
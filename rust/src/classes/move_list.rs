@@ -0,0 +1,95 @@
+use crate::{classes::ChessBoard2D, types};
+use godot::{
+    engine::{IItemList, ItemList},
+    prelude::*,
+};
+
+/// Lists the moves of a `ChessBoard2D`'s game in SAN, a numbered row per move pair. Clicking a
+/// move shows the position after it.
+#[derive(GodotClass)]
+#[class(base = ItemList)]
+pub struct MoveList {
+    #[export]
+    board: NodePath,
+    base: Base<ItemList>,
+}
+
+#[godot_api]
+impl IItemList for MoveList {
+    fn init(base: Base<ItemList>) -> Self {
+        Self {
+            board: NodePath::default(),
+            base,
+        }
+    }
+
+    fn ready(&mut self) {
+        self.base_mut().set_max_columns(3);
+        self.base_mut().set_same_column_width(true);
+        let callable = self.base().callable("on_item_selected");
+        self.base_mut().connect("item_selected".into(), callable);
+
+        let mut board = self.base().get_node_as::<Node>(self.board.clone());
+        board.connect(
+            "history_changed".into(),
+            self.base().callable("on_history_changed"),
+        );
+        self.on_history_changed();
+    }
+}
+
+#[godot_api]
+impl MoveList {
+    #[func]
+    fn on_history_changed(&mut self) {
+        let board = self.base().get_node_as::<ChessBoard2D>(self.board.clone());
+        let board = board.bind();
+        let first = board.first_board();
+        let viewed_ply = board.viewed_ply();
+
+        self.base_mut().clear();
+        let mut number = first.fullmove_number;
+        if first.turn == types::Color::Black {
+            self.add_label(format!("{}.", number));
+            self.add_label("...".into());
+        }
+        let mut current = None;
+        for (i, san) in board.san_moves().into_iter().enumerate() {
+            let ply = i + 1;
+            let white = (first.turn == types::Color::White) == (i % 2 == 0);
+            if white {
+                self.add_label(format!("{}.", number));
+            } else {
+                number += 1;
+            }
+            let index = self.base_mut().add_item(san.into());
+            self.base_mut()
+                .set_item_metadata(index, (ply as i64).to_variant());
+            if ply == viewed_ply {
+                current = Some(index);
+            }
+        }
+
+        match current {
+            Some(index) => {
+                self.base_mut().select(index);
+                self.base_mut().ensure_current_is_visible();
+            }
+            None => self.base_mut().deselect_all(),
+        }
+    }
+
+    #[func]
+    fn on_item_selected(&mut self, index: i64) {
+        let ply = self.base().get_item_metadata(index as i32);
+        // Deferred, since the board calls back into `on_history_changed`
+        let mut board = self.base().get_node_as::<Node>(self.board.clone());
+        board.call_deferred("view_ply".into(), &[ply]);
+    }
+
+    /// Adds a cell that can't be selected, like a move number.
+    fn add_label(&mut self, text: String) {
+        let index = self.base_mut().add_item(text.into());
+        self.base_mut().set_item_selectable(index, false);
+    }
+}
@@ -0,0 +1,313 @@
+use crate::types::Color;
+use std::time::Duration;
+
+/// What a player gets back for each move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bonus {
+    None,
+    /// Fischer increment, added after every move.
+    Increment(Duration),
+    /// Simple delay. The clock only starts running once the delay is up.
+    Delay(Duration),
+    /// Bronstein delay. After every move, the time used is given back, up to the delay.
+    Bronstein(Duration),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stage {
+    /// Moves to be made in this stage. If `None`, the stage lasts the rest of the game.
+    pub moves: Option<u32>,
+    pub time: Duration,
+    pub bonus: Bonus,
+}
+
+/// A sequence of stages, like 40 moves in 90 minutes followed by 30 minutes for the rest of the
+/// game, both with a 30 second increment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClockFormat {
+    pub stages: Vec<Stage>,
+}
+
+impl ClockFormat {
+    /// Parses a PGN-style time control: stages separated by `:`, each being
+    /// `[moves/]seconds[+increment]`, like `300+2` or `40/5400+30:1800+30`. As an extension, `d`
+    /// in place of `+` gives a simple delay and `b` a Bronstein delay, like `600d5`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut stages = Vec::new();
+
+        for stage in s.split(':') {
+            let (moves, rest) = match stage.split_once('/') {
+                Some((moves, rest)) => (
+                    Some(
+                        moves
+                            .parse::<u32>()
+                            .ok()
+                            .filter(|moves| *moves > 0)
+                            .ok_or(format!("Invalid move count {}", moves))?,
+                    ),
+                    rest,
+                ),
+                None => (None, stage),
+            };
+
+            let (time, bonus) = match rest.find(['+', 'd', 'b']) {
+                Some(i) => {
+                    let seconds = parse_seconds(&rest[i + 1..])?;
+                    let bonus = match &rest[i..i + 1] {
+                        "+" => Bonus::Increment(seconds),
+                        "d" => Bonus::Delay(seconds),
+                        _ => Bonus::Bronstein(seconds),
+                    };
+                    (&rest[..i], bonus)
+                }
+                None => (rest, Bonus::None),
+            };
+
+            stages.push(Stage {
+                moves,
+                time: parse_seconds(time)?,
+                bonus,
+            });
+        }
+
+        Ok(Self { stages })
+    }
+}
+
+fn parse_seconds(s: &str) -> Result<Duration, String> {
+    s.parse::<f64>()
+        .ok()
+        .filter(|seconds| *seconds >= 0.0)
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or(format!("Invalid number of seconds {}", s))
+}
+
+fn side(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+pub struct Clock {
+    format: ClockFormat,
+    remaining: [Duration; 2],
+    stage: [usize; 2],
+    /// Moves made in the current stage.
+    stage_moves: [u32; 2],
+    running: Option<Color>,
+    /// Time spent on the current move, including any delay.
+    used: Duration,
+}
+
+impl Clock {
+    pub fn new(format: ClockFormat) -> Self {
+        let time = format.stages[0].time;
+        Self {
+            format,
+            remaining: [time; 2],
+            stage: [0; 2],
+            stage_moves: [0; 2],
+            running: None,
+            used: Duration::ZERO,
+        }
+    }
+
+    fn current_stage(&self, color: Color) -> &Stage {
+        &self.format.stages[self.stage[side(color)]]
+    }
+
+    pub fn remaining(&self, color: Color) -> Duration {
+        self.remaining[side(color)]
+    }
+
+    pub fn running(&self) -> Option<Color> {
+        self.running
+    }
+
+    /// Moves left until the next stage, if there is one.
+    pub fn moves_to_go(&self, color: Color) -> Option<u32> {
+        self.current_stage(color)
+            .moves
+            .map(|moves| moves - self.stage_moves[side(color)])
+    }
+
+    /// The most time `color` gets back per move.
+    pub fn increment(&self, color: Color) -> Duration {
+        match self.current_stage(color).bonus {
+            Bonus::None => Duration::ZERO,
+            Bonus::Increment(bonus) | Bonus::Delay(bonus) | Bonus::Bronstein(bonus) => bonus,
+        }
+    }
+
+    pub fn start(&mut self, color: Color) {
+        self.running = Some(color);
+        self.used = Duration::ZERO;
+    }
+
+    pub fn stop(&mut self) {
+        self.running = None;
+    }
+
+    /// Runs the clock of the side to move. Returns the side whose flag fell, if any.
+    pub fn tick(&mut self, elapsed: Duration) -> Option<Color> {
+        let color = self.running?;
+
+        // Under a simple delay, only the part of the move past the delay counts
+        let delay = match self.current_stage(color).bonus {
+            Bonus::Delay(delay) => delay,
+            _ => Duration::ZERO,
+        };
+        let charged = (self.used + elapsed).saturating_sub(delay) - self.used.saturating_sub(delay);
+        self.used += elapsed;
+
+        let remaining = &mut self.remaining[side(color)];
+        *remaining = remaining.saturating_sub(charged);
+        if remaining.is_zero() {
+            self.running = None;
+            return Some(color);
+        }
+        None
+    }
+
+    /// Ends the running side's move and starts the other side's clock.
+    pub fn press(&mut self) {
+        let Some(color) = self.running else {
+            return;
+        };
+        let i = side(color);
+
+        match self.current_stage(color).bonus {
+            Bonus::Increment(increment) => self.remaining[i] += increment,
+            Bonus::Bronstein(delay) => self.remaining[i] += self.used.min(delay),
+            Bonus::None | Bonus::Delay(_) => {}
+        }
+
+        self.stage_moves[i] += 1;
+        if self.current_stage(color).moves == Some(self.stage_moves[i]) {
+            // The last stage repeats if it has a move count
+            if self.stage[i] + 1 < self.format.stages.len() {
+                self.stage[i] += 1;
+            }
+            self.stage_moves[i] = 0;
+            self.remaining[i] += self.current_stage(color).time;
+        }
+
+        self.start(!color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn parses_a_single_stage() {
+        let format = ClockFormat::parse("300+2").unwrap();
+        assert_eq!(
+            format.stages,
+            vec![Stage {
+                moves: None,
+                time: seconds(300),
+                bonus: Bonus::Increment(seconds(2)),
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_several_stages() {
+        let format = ClockFormat::parse("40/5400+30:1800+30").unwrap();
+        assert_eq!(
+            format.stages,
+            vec![
+                Stage {
+                    moves: Some(40),
+                    time: seconds(5400),
+                    bonus: Bonus::Increment(seconds(30)),
+                },
+                Stage {
+                    moves: None,
+                    time: seconds(1800),
+                    bonus: Bonus::Increment(seconds(30)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_delays_and_fractions() {
+        let stage = |s| ClockFormat::parse(s).unwrap().stages[0];
+        assert_eq!(stage("600d5").bonus, Bonus::Delay(seconds(5)));
+        assert_eq!(stage("600b5").bonus, Bonus::Bronstein(seconds(5)));
+        assert_eq!(stage("60").bonus, Bonus::None);
+        assert_eq!(stage("0.5").time, Duration::from_millis(500));
+    }
+
+    #[test]
+    fn rejects_invalid_time_controls() {
+        for s in [
+            "", "abc", "-5", "300+", "x/300", "300+2:", "inf", "1e30", "300+inf", "0/60",
+        ] {
+            assert!(ClockFormat::parse(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn adds_the_increment_and_switches_sides() {
+        let mut clock = Clock::new(ClockFormat::parse("60+2").unwrap());
+        clock.start(Color::White);
+        assert_eq!(clock.tick(seconds(5)), None);
+        clock.press();
+        assert_eq!(clock.remaining(Color::White), seconds(57));
+        assert_eq!(clock.running(), Some(Color::Black));
+    }
+
+    #[test]
+    fn simple_delay_runs_before_the_clock() {
+        let mut clock = Clock::new(ClockFormat::parse("60d5").unwrap());
+        clock.start(Color::White);
+        clock.tick(seconds(3));
+        assert_eq!(clock.remaining(Color::White), seconds(60));
+        clock.tick(seconds(4));
+        assert_eq!(clock.remaining(Color::White), seconds(58));
+    }
+
+    #[test]
+    fn bronstein_delay_gives_back_at_most_the_delay() {
+        let mut clock = Clock::new(ClockFormat::parse("60b5").unwrap());
+        clock.start(Color::White);
+        clock.tick(seconds(3));
+        clock.press();
+        assert_eq!(clock.remaining(Color::White), seconds(60));
+
+        clock.tick(seconds(8));
+        clock.press();
+        assert_eq!(clock.remaining(Color::Black), seconds(57));
+    }
+
+    #[test]
+    fn moves_on_to_the_next_stage() {
+        let mut clock = Clock::new(ClockFormat::parse("2/60:30").unwrap());
+        clock.start(Color::White);
+        assert_eq!(clock.moves_to_go(Color::White), Some(2));
+        clock.press();
+        clock.press();
+        assert_eq!(clock.moves_to_go(Color::White), Some(1));
+        clock.press();
+        assert_eq!(clock.remaining(Color::White), seconds(90));
+        assert_eq!(clock.moves_to_go(Color::White), None);
+    }
+
+    #[test]
+    fn flag_falls_at_zero() {
+        let mut clock = Clock::new(ClockFormat::parse("10").unwrap());
+        clock.start(Color::Black);
+        assert_eq!(clock.tick(seconds(11)), Some(Color::Black));
+        assert_eq!(clock.remaining(Color::Black), Duration::ZERO);
+        assert_eq!(clock.running(), None);
+    }
+}
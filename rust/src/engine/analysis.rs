@@ -0,0 +1,86 @@
+use crate::{
+    engine::{mate_in, search_with_progress, SearchLimits},
+    types::{Board, Color},
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+#[derive(Clone, Debug)]
+pub struct AnalysisLine {
+    /// Centipawns, from white's point of view.
+    pub score: i32,
+    /// Moves until mate, from white's point of view, if the line ends in mate.
+    pub mate: Option<i32>,
+    pub depth: u8,
+    /// The principal variation in SAN.
+    pub moves: Vec<String>,
+}
+
+/// Searches a position on a background thread until dropped, deepening as it goes.
+pub struct Analysis {
+    stop: Arc<AtomicBool>,
+    receiver: mpsc::Receiver<Vec<AnalysisLine>>,
+}
+
+impl Analysis {
+    pub fn start(board: Board, lines: usize) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+
+        let limits = SearchLimits {
+            multipv: lines,
+            stop: Some(stop.clone()),
+            ..Default::default()
+        };
+        thread::spawn(move || {
+            let sign = match board.turn {
+                Color::White => 1,
+                Color::Black => -1,
+            };
+            search_with_progress(&board, &limits, |depth, lines| {
+                let lines = lines
+                    .iter()
+                    .map(|line| {
+                        // Each move's SAN depends on the position before it
+                        let mut position = board;
+                        let moves = line
+                            .moves
+                            .iter()
+                            .map(|m| {
+                                let san = position.san(m);
+                                position.make_move(*m);
+                                san
+                            })
+                            .collect();
+
+                        AnalysisLine {
+                            score: sign * line.score,
+                            mate: mate_in(line.score).map(|mate| sign * mate),
+                            depth,
+                            moves,
+                        }
+                    })
+                    .collect();
+                let _ = sender.send(lines);
+            });
+        });
+
+        Self { stop, receiver }
+    }
+
+    /// The deepest lines found since the last call, if there are any new ones.
+    pub fn latest(&self) -> Option<Vec<AnalysisLine>> {
+        self.receiver.try_iter().last()
+    }
+}
+
+impl Drop for Analysis {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,12 @@
+pub mod analysis;
+pub use analysis::*;
+pub mod evaluation;
+pub use evaluation::*;
+pub mod move_search;
+pub use move_search::*;
+pub mod search;
+pub use search::*;
+pub mod skill;
+pub use skill::*;
+pub mod time;
+pub use time::*;
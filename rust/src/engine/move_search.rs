@@ -0,0 +1,44 @@
+use crate::types::Move;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+/// Looks for a move on a background thread. The search is told to stop when this is dropped, so
+/// one that's no longer wanted doesn't run on to its limits.
+pub struct MoveSearch {
+    stop: Arc<AtomicBool>,
+    receiver: mpsc::Receiver<Option<Move>>,
+}
+
+impl MoveSearch {
+    /// Runs `find` on a new thread, passing it the flag its search should stop on.
+    pub fn start<F>(find: F) -> Self
+    where
+        F: FnOnce(Arc<AtomicBool>) -> Option<Move> + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+
+        let flag = stop.clone();
+        thread::spawn(move || {
+            let _ = sender.send(find(flag));
+        });
+
+        Self { stop, receiver }
+    }
+
+    /// The move found, once the search has finished. It's `Some(None)` if there was no move.
+    pub fn result(&self) -> Option<Option<Move>> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl Drop for MoveSearch {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
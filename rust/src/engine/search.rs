@@ -0,0 +1,293 @@
+use crate::{
+    engine::{evaluate, piece_value, TimeControl, TimeManager},
+    types::{Board, Move, Piece},
+};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+pub const MATE: i32 = 100_000;
+const INFINITY: i32 = MATE + 1;
+pub const MAX_DEPTH: u8 = 64;
+
+#[derive(Clone)]
+pub struct SearchLimits {
+    /// If `None`, deepens until stopped some other way.
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    /// How many root moves get an exact score. The rest are only known to be worse.
+    pub multipv: usize,
+    /// Set from another thread to end the search early.
+    pub stop: Option<Arc<AtomicBool>>,
+    pub time: Option<TimeControl>,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        Self {
+            depth: None,
+            nodes: None,
+            multipv: 1,
+            stop: None,
+            time: None,
+        }
+    }
+}
+
+/// A principal variation and its score, from the point of view of the side to move at the root.
+#[derive(Clone, Debug)]
+pub struct Line {
+    pub score: i32,
+    pub moves: Vec<Move>,
+}
+
+#[derive(Debug, Default)]
+pub struct SearchResult {
+    /// Best first, at most `multipv` long.
+    pub lines: Vec<Line>,
+}
+
+impl SearchResult {
+    pub fn best_move(&self) -> Option<Move> {
+        self.lines
+            .first()
+            .and_then(|line| line.moves.first().copied())
+    }
+}
+
+/// Moves until mate, if `score` is a mate score. Negative when the side to move is getting mated.
+pub fn mate_in(score: i32) -> Option<i32> {
+    if score.abs() < MATE - MAX_DEPTH as i32 {
+        return None;
+    }
+    let plies = MATE - score.abs();
+    Some(score.signum() * (plies + 1) / 2)
+}
+
+pub fn search(board: &Board, limits: &SearchLimits) -> SearchResult {
+    search_with_progress(board, limits, |_, _| {})
+}
+
+/// Like `search`, but calls `report` with the depth and lines after every completed iteration.
+pub fn search_with_progress(
+    board: &Board,
+    limits: &SearchLimits,
+    mut report: impl FnMut(u8, &[Line]),
+) -> SearchResult {
+    Search {
+        limits,
+        time: limits.time.as_ref().map(TimeManager::new),
+        nodes: 0,
+        stopped: false,
+        can_stop: false,
+    }
+    .run(board, &mut report)
+}
+
+struct Search<'a> {
+    limits: &'a SearchLimits,
+    time: Option<TimeManager>,
+    nodes: u64,
+    stopped: bool,
+    /// Stays false until the first iteration is done, so there's always a move to return.
+    can_stop: bool,
+}
+
+impl Search<'_> {
+    fn run(&mut self, board: &Board, report: &mut dyn FnMut(u8, &[Line])) -> SearchResult {
+        let mut result = SearchResult::default();
+        let mut root_moves = board.legal_moves();
+        if root_moves.is_empty() {
+            return result;
+        }
+        order_moves(board, &mut root_moves);
+
+        let multipv = self.limits.multipv.max(1);
+        let max_depth = self.limits.depth.unwrap_or(MAX_DEPTH).max(1);
+
+        for depth in 1..=max_depth {
+            let mut lines: Vec<Line> = Vec::new();
+
+            for m in &root_moves {
+                let mut child = *board;
+                child.make_move(*m);
+
+                // Moves only need an exact score if they might make it into the top `multipv`
+                let alpha = if lines.len() >= multipv {
+                    lines[multipv - 1].score
+                } else {
+                    -INFINITY
+                };
+
+                let mut pv = Vec::new();
+                let score = -self.negamax(&child, depth - 1, 1, -INFINITY, -alpha, &mut pv);
+                if self.stopped {
+                    break;
+                }
+
+                pv.insert(0, *m);
+                lines.push(Line { score, moves: pv });
+                lines.sort_by_key(|line| -line.score);
+            }
+
+            if self.stopped {
+                break;
+            }
+
+            root_moves = lines.iter().map(|line| line.moves[0]).collect();
+            lines.truncate(multipv);
+            let previous = result.lines.first().map(|line| (line.score, line.moves[0]));
+            result.lines = lines;
+            self.can_stop = true;
+            report(depth, &result.lines);
+
+            let best = &result.lines[0];
+            if best.score.abs() >= MATE - depth as i32 {
+                break;
+            }
+
+            if let (Some(time), Some((previous_score, previous_move))) = (&self.time, previous) {
+                if !time.should_deepen(previous_score - best.score, previous_move != best.moves[0])
+                {
+                    break;
+                }
+            }
+        }
+
+        result
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if !self.can_stop {
+            return false;
+        }
+        // Checking the clock is comparatively slow, so only do it every so often
+        let out_of_time = self.nodes.is_multiple_of(1024)
+            && self
+                .time
+                .as_ref()
+                .is_some_and(|time| time.hard_limit_reached());
+        if out_of_time
+            || self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes)
+            || self
+                .limits
+                .stop
+                .as_ref()
+                .is_some_and(|stop| stop.load(Ordering::Relaxed))
+        {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+
+    fn negamax(
+        &mut self,
+        board: &Board,
+        depth: u8,
+        ply: u8,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+
+        if board.halfmove_clock >= 100 {
+            return 0;
+        }
+
+        let mut moves = board.legal_moves();
+        if moves.is_empty() {
+            return if board.is_in_check(board.turn) {
+                -MATE + ply as i32
+            } else {
+                0
+            };
+        }
+
+        if depth == 0 {
+            return self.quiesce(board, alpha, beta);
+        }
+
+        order_moves(board, &mut moves);
+        for m in moves {
+            let mut child = *board;
+            child.make_move(m);
+
+            let mut child_pv = Vec::new();
+            let score = -self.negamax(&child, depth - 1, ply + 1, -beta, -alpha, &mut child_pv);
+            if self.stopped {
+                return 0;
+            }
+
+            if score >= beta {
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(m);
+                pv.extend(child_pv);
+            }
+        }
+
+        alpha
+    }
+
+    /// Only searches captures and queen promotions, so the static evaluation isn't taken in the
+    /// middle of an exchange.
+    fn quiesce(&mut self, board: &Board, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+
+        let stand_pat = evaluate(board);
+        if stand_pat >= beta {
+            return beta;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves = board
+            .legal_moves()
+            .into_iter()
+            .filter(|m| board.capture_square(m).is_some() || m.promotion == Some(Piece::Queen))
+            .collect::<Vec<Move>>();
+        order_moves(board, &mut moves);
+
+        for m in moves {
+            let mut child = *board;
+            child.make_move(m);
+
+            let score = -self.quiesce(&child, -beta, -alpha);
+            if self.stopped {
+                return 0;
+            }
+
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+
+        alpha
+    }
+}
+
+/// Most valuable victim, least valuable attacker first, with promotions ahead of quiet moves.
+fn order_moves(board: &Board, moves: &mut [Move]) {
+    moves.sort_by_cached_key(|m| {
+        let mut guess = 0;
+        if let Some(captured) = board.capture_square(m).and_then(|i| board.troops[i]) {
+            let attacker = board.troops[m.start].unwrap();
+            guess += 10 * piece_value(captured.piece) - piece_value(attacker.piece);
+        }
+        if let Some(piece) = m.promotion {
+            guess += piece_value(piece);
+        }
+        -guess
+    });
+}
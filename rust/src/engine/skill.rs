@@ -0,0 +1,128 @@
+use crate::{
+    engine::{search, Line, SearchLimits, TimeControl},
+    tablebase::Tablebase,
+    types::{Board, Move},
+};
+use std::{
+    sync::{atomic::AtomicBool, Arc},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const MAX_SKILL_LEVEL: u8 = 20;
+
+/// How strongly the computer plays. Below the maximum level, the search is cut short and the
+/// move is picked from the top few lines with some noise, and occasionally on purpose from the
+/// worse ones.
+#[derive(Clone, Copy, Debug)]
+pub struct Skill {
+    level: u8,
+}
+
+impl Skill {
+    pub fn from_level(level: i64) -> Self {
+        Self {
+            level: level.clamp(0, MAX_SKILL_LEVEL as i64) as u8,
+        }
+    }
+
+    /// Very rough: level 0 plays somewhere around 800, and every level adds about 80.
+    pub fn from_elo(elo: i64) -> Self {
+        Self::from_level((elo - 800) / 80)
+    }
+
+    pub fn is_full_strength(&self) -> bool {
+        self.level == MAX_SKILL_LEVEL
+    }
+
+    pub fn depth(&self) -> u8 {
+        1 + self.level / 4
+    }
+
+    pub fn nodes(&self) -> u64 {
+        1000 << (self.level / 2)
+    }
+
+    /// Largest amount of noise added to each line's score, in centipawns.
+    pub fn noise(&self) -> i32 {
+        (MAX_SKILL_LEVEL - self.level) as i32 * 10
+    }
+
+    /// Chance, in percent, of deliberately picking something other than the best line.
+    pub fn mistake_chance(&self) -> u64 {
+        (MAX_SKILL_LEVEL - self.level) as u64
+    }
+
+    /// On a clock, full strength is only limited by time. Lower levels keep their caps either way.
+    pub fn limits(&self, time: Option<TimeControl>) -> SearchLimits {
+        let capped = !self.is_full_strength() || time.is_none();
+        SearchLimits {
+            depth: capped.then(|| self.depth()),
+            nodes: capped.then(|| self.nodes()),
+            multipv: if self.is_full_strength() { 1 } else { 4 },
+            time,
+            ..Default::default()
+        }
+    }
+
+    /// `stop` ends the search early, once the move is no longer wanted.
+    pub fn choose_move(
+        &self,
+        board: &Board,
+        tablebase: Option<&Tablebase>,
+        time: Option<TimeControl>,
+        stop: Option<Arc<AtomicBool>>,
+    ) -> Option<Move> {
+        if self.is_full_strength() {
+            if let Some(m) = tablebase.and_then(|t| t.best_move(board)) {
+                return Some(m);
+            }
+        }
+
+        let limits = SearchLimits {
+            stop,
+            ..self.limits(time)
+        };
+        let result = search(board, &limits);
+        self.pick(&result.lines, &mut Rng::new())
+    }
+
+    fn pick(&self, lines: &[Line], rng: &mut Rng) -> Option<Move> {
+        if self.is_full_strength() || lines.len() < 2 {
+            return lines.first().and_then(|line| line.moves.first().copied());
+        }
+
+        let line = if rng.below(100) < self.mistake_chance() {
+            &lines[1 + rng.below(lines.len() as u64 - 1) as usize]
+        } else {
+            lines
+                .iter()
+                .max_by_key(|line| line.score + rng.below(self.noise() as u64 + 1) as i32)
+                .unwrap()
+        };
+        line.moves.first().copied()
+    }
+}
+
+/// Xorshift. Good enough to make the computer's mistakes unpredictable.
+struct Rng(u64);
+
+impl Rng {
+    fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Self(seed | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
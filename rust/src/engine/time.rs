@@ -0,0 +1,126 @@
+use std::time::{Duration, Instant};
+
+/// Kept back from every allocation to cover the time it takes to actually play the move.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+/// Assumed number of moves left in the game when the time control doesn't say.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// The clock situation of the side to move.
+#[derive(Clone, Copy, Debug)]
+pub struct TimeControl {
+    pub remaining: Duration,
+    pub increment: Duration,
+    /// Moves until the next time control, if there is one.
+    pub moves_to_go: Option<u32>,
+}
+
+/// Decides how long a search may take. The optimum is where the search stops deepening in a
+/// quiet position, while the maximum is a hard limit the search is cut off at.
+#[derive(Clone, Copy, Debug)]
+pub struct TimeManager {
+    start: Instant,
+    optimum: Duration,
+    maximum: Duration,
+}
+
+impl TimeManager {
+    pub fn new(control: &TimeControl) -> Self {
+        let available = control.remaining.saturating_sub(MOVE_OVERHEAD);
+        let moves_to_go = control
+            .moves_to_go
+            .unwrap_or(DEFAULT_MOVES_TO_GO)
+            .clamp(1, DEFAULT_MOVES_TO_GO);
+
+        // With a single move to go there's no need to save anything for later
+        let maximum = if moves_to_go == 1 {
+            available.mul_f64(0.9)
+        } else {
+            available.mul_f64(0.75)
+        };
+        let optimum = (available / moves_to_go + control.increment.mul_f64(0.75)).min(maximum);
+        let maximum = maximum.min(optimum * 5);
+
+        Self {
+            start: Instant::now(),
+            optimum,
+            maximum,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    pub fn hard_limit_reached(&self) -> bool {
+        self.elapsed() >= self.maximum
+    }
+
+    /// Whether to start another iteration. A falling score or a best move that keeps changing
+    /// means the position is unclear, so more time is spent on it.
+    pub fn should_deepen(&self, score_drop: i32, best_move_changed: bool) -> bool {
+        let mut scale = 1.0;
+        if score_drop > 0 {
+            scale += score_drop.min(150) as f64 / 150.0;
+        }
+        if best_move_changed {
+            scale *= 1.4;
+        }
+
+        // The next iteration usually takes longer than all the previous ones put together, so
+        // don't bother starting it past half the budget
+        let budget = self.optimum.mul_f64(scale).min(self.maximum);
+        self.elapsed() < budget / 2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager(remaining: u64, increment: u64, moves_to_go: Option<u32>) -> TimeManager {
+        TimeManager::new(&TimeControl {
+            remaining: Duration::from_secs(remaining),
+            increment: Duration::from_secs(increment),
+            moves_to_go,
+        })
+    }
+
+    #[test]
+    fn spreads_time_over_the_default_moves_to_go() {
+        let time = manager(60, 0, None);
+        let available = Duration::from_secs(60) - MOVE_OVERHEAD;
+        assert_eq!(time.optimum, available / DEFAULT_MOVES_TO_GO);
+        assert_eq!(time.maximum, time.optimum * 5);
+    }
+
+    #[test]
+    fn adds_most_of_the_increment() {
+        let without = manager(60, 0, None);
+        let with = manager(60, 2, None);
+        assert_eq!(with.optimum - without.optimum, Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn uses_most_of_the_time_with_one_move_to_go() {
+        let time = manager(10, 0, Some(1));
+        let available = Duration::from_secs(10) - MOVE_OVERHEAD;
+        assert_eq!(time.maximum, available.mul_f64(0.9));
+        assert_eq!(time.optimum, time.maximum);
+    }
+
+    #[test]
+    fn never_plans_past_the_remaining_time() {
+        let time = manager(0, 5, Some(3));
+        assert_eq!(time.optimum, Duration::ZERO);
+        assert_eq!(time.maximum, Duration::ZERO);
+        assert!(time.hard_limit_reached());
+        assert!(!time.should_deepen(0, false));
+    }
+
+    #[test]
+    fn deepens_with_time_to_spare() {
+        let time = manager(600, 0, None);
+        assert!(!time.hard_limit_reached());
+        assert!(time.should_deepen(0, false));
+    }
+}
@@ -1,4 +1,8 @@
+mod annotation;
 mod classes;
+mod clock;
+mod engine;
+mod tablebase;
 mod types;
 
 use godot::prelude::*;
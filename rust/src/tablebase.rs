@@ -0,0 +1,133 @@
+use crate::types::{Board, Color, Move, Piece};
+use shakmaty::{fen::Fen, CastlingMode, Chess, PositionError};
+use shakmaty_syzygy::{AmbiguousWdl, Tablebase as SyzygyTablebase};
+
+/// Game theoretical value of a position, from the point of view of the side to move.
+///
+/// Cursed wins and blessed losses are wins and losses that can't be forced before the fifty-move
+/// rule kicks in, so they count as draws in practice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Probe {
+    pub wdl: Wdl,
+    /// Distance to zeroing (capture or pawn move), in plies. Positive when the side to move is
+    /// winning, negative when it is losing.
+    pub dtz: i32,
+}
+
+impl Probe {
+    pub fn describe(&self, turn: Color) -> String {
+        match self.wdl {
+            Wdl::Win => format!("Tablebase win for {} (DTZ {})", turn, self.dtz.abs()),
+            Wdl::Loss => format!("Tablebase loss for {} (DTZ {})", turn, self.dtz.abs()),
+            Wdl::CursedWin | Wdl::Draw | Wdl::BlessedLoss => "Tablebase draw".into(),
+        }
+    }
+}
+
+/// Syzygy endgame tablebases loaded from a directory on disk.
+pub struct Tablebase {
+    inner: SyzygyTablebase<Chess>,
+}
+
+impl Tablebase {
+    pub fn open(directory: &str) -> Result<Self, String> {
+        let mut inner = SyzygyTablebase::new();
+        let tables = inner
+            .add_directory(directory)
+            .map_err(|e| format!("Failed to read {}: {}", directory, e))?;
+        if tables == 0 {
+            return Err(format!("No tablebase files in {}", directory));
+        }
+
+        Ok(Self { inner })
+    }
+
+    pub fn max_pieces(&self) -> usize {
+        self.inner.max_pieces()
+    }
+
+    /// Returns `None` if the position has too many pieces for the loaded tables, still has
+    /// castling rights, or is otherwise missing from the tables.
+    pub fn probe(&self, board: &Board) -> Option<Probe> {
+        if board.troops.iter().flatten().count() > self.max_pieces() {
+            return None;
+        }
+
+        let position = to_position(board)?;
+        let wdl = self.inner.probe_wdl(&position).ok()?;
+        let dtz = self.inner.probe_dtz(&position).ok()?.ignore_rounding().0;
+
+        // The tables can't always tell whether a win still comes in time once some of the
+        // fifty moves have gone, so that's decided with the halfmove clock
+        let in_time = dtz.abs() + board.halfmove_clock as i32 <= 100;
+        let wdl = match wdl {
+            AmbiguousWdl::Win => Wdl::Win,
+            AmbiguousWdl::MaybeWin if in_time => Wdl::Win,
+            AmbiguousWdl::MaybeWin | AmbiguousWdl::CursedWin => Wdl::CursedWin,
+            AmbiguousWdl::Draw => Wdl::Draw,
+            AmbiguousWdl::BlessedLoss => Wdl::BlessedLoss,
+            AmbiguousWdl::MaybeLoss if in_time => Wdl::Loss,
+            AmbiguousWdl::MaybeLoss => Wdl::BlessedLoss,
+            AmbiguousWdl::Loss => Wdl::Loss,
+        };
+
+        Some(Probe { wdl, dtz })
+    }
+
+    /// Picks the move that keeps the best result, winning as fast as possible or losing as slowly
+    /// as possible. Distances count the plies already used towards the fifty-move rule, since
+    /// those bring it closer too. Returns `None` if any of the resulting positions can't be
+    /// probed.
+    pub fn best_move(&self, board: &Board) -> Option<Move> {
+        let mut best: Option<(Move, (Wdl, bool, i32))> = None;
+
+        for m in board.legal_moves() {
+            let mut child = *board;
+            let zeroing = child.make_move(m).is_some()
+                || board.troops[m.start].is_some_and(|t| t.piece == Piece::Pawn);
+
+            if child.legal_moves().is_empty() && child.is_in_check(child.turn) {
+                return Some(m);
+            }
+
+            let probe = self.probe(&child)?;
+            // The child is probed from the opponent's side, so flip it back around
+            let wdl = match probe.wdl {
+                Wdl::Loss => Wdl::Win,
+                Wdl::BlessedLoss => Wdl::CursedWin,
+                Wdl::Draw => Wdl::Draw,
+                Wdl::CursedWin => Wdl::BlessedLoss,
+                Wdl::Win => Wdl::Loss,
+            };
+            let distance = probe.dtz.abs() + child.halfmove_clock as i32;
+            let key = match wdl {
+                Wdl::Win | Wdl::CursedWin => (wdl, zeroing, -distance),
+                Wdl::Loss | Wdl::BlessedLoss => (wdl, !zeroing, distance),
+                Wdl::Draw => (wdl, false, 0),
+            };
+
+            if best.as_ref().map_or(true, |(_, best_key)| key > *best_key) {
+                best = Some((m, key));
+            }
+        }
+
+        best.map(|(m, _)| m)
+    }
+}
+
+fn to_position(board: &Board) -> Option<Chess> {
+    let fen = board.to_fen().parse::<Fen>().ok()?;
+    fen.into_position(CastlingMode::Standard)
+        .or_else(PositionError::ignore_invalid_castling_rights)
+        .or_else(PositionError::ignore_invalid_ep_square)
+        .ok()
+}
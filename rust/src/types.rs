@@ -3,7 +3,7 @@ use std::{
     ops::Not,
 };
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Piece {
     King,
     Queen,
@@ -29,6 +29,44 @@ impl Display for Piece {
     }
 }
 
+impl Piece {
+    /// Uppercase letter used in FEN and SAN.
+    pub fn letter(self) -> char {
+        match self {
+            Piece::King => 'K',
+            Piece::Queen => 'Q',
+            Piece::Rook => 'R',
+            Piece::Bishop => 'B',
+            Piece::Knight => 'N',
+            Piece::Pawn => 'P',
+        }
+    }
+
+    /// Conventional material value, like 3 for a knight.
+    pub fn points(self) -> i32 {
+        match self {
+            Piece::King => 0,
+            Piece::Queen => 9,
+            Piece::Rook => 5,
+            Piece::Bishop | Piece::Knight => 3,
+            Piece::Pawn => 1,
+        }
+    }
+
+    /// The opposite of `letter`, in either case.
+    pub fn from_letter(letter: char) -> Option<Self> {
+        match letter.to_ascii_uppercase() {
+            'K' => Some(Piece::King),
+            'Q' => Some(Piece::Queen),
+            'R' => Some(Piece::Rook),
+            'B' => Some(Piece::Bishop),
+            'N' => Some(Piece::Knight),
+            'P' => Some(Piece::Pawn),
+            _ => None,
+        }
+    }
+}
+
 /// Precalculated.
 /// For each square, `[north, south, west, east, northwest, southeast, northeast, southwest]`
 const NUM_SQUARES_TO_EDGE: [[i8; 8]; 64] = [
@@ -126,7 +164,30 @@ fn square_to_index(square: (char, u8)) -> Result<usize, String> {
     Ok(((8 * rank) + file) as usize)
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+pub fn index_to_square(index: usize) -> (char, u8) {
+    let file = (b'a' + (index % 8) as u8) as char;
+    let rank = 8 - (index / 8) as u8;
+    (file, rank)
+}
+
+/// Parses a square name like "e4".
+pub fn parse_square(name: &str) -> Result<usize, String> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(file), Some(rank), None) => {
+            let rank = rank.to_digit(10).ok_or(format!("Invalid rank {}", rank))?;
+            square_to_index((file, rank as u8))
+        }
+        _ => Err(format!("Invalid square {}", name)),
+    }
+}
+
+pub fn square_name(index: usize) -> String {
+    let (file, rank) = index_to_square(index);
+    format!("{}{}", file, rank)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Color {
     Black,
     White,
@@ -155,23 +216,16 @@ impl Not for Color {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct Troop {
     pub color: Color,
     pub piece: Piece,
 }
 impl std::fmt::Debug for Troop {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut piece = match self.piece {
-            Piece::King => 'k',
-            Piece::Queen => 'q',
-            Piece::Rook => 'r',
-            Piece::Bishop => 'b',
-            Piece::Knight => 'n',
-            Piece::Pawn => 'p',
-        };
-        if self.color == Color::White {
-            piece = piece.to_ascii_uppercase();
+        let mut piece = self.piece.letter();
+        if self.color == Color::Black {
+            piece = piece.to_ascii_lowercase();
         }
         write!(f, "{}", piece)
     }
@@ -183,13 +237,17 @@ impl Display for Troop {
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct Board {
     pub troops: [Option<Troop>; 64],
     pub turn: Color,
     pub castling_rights: CastlingRights,
     pub en_passant_target: Option<usize>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
 }
 
+#[derive(Clone, Copy)]
 pub struct CastlingRights {
     white_king_side: bool,
     white_queen_side: bool,
@@ -236,11 +294,20 @@ impl Board {
             }
         }
 
+        let halfmove_clock = fields[4]
+            .parse::<u32>()
+            .map_err(|_| "Invalid halfmove clock".to_string())?;
+        let fullmove_number = fields[5]
+            .parse::<u32>()
+            .map_err(|_| "Invalid fullmove number".to_string())?;
+
         let mut board = Self {
             troops: [None; 64],
             turn,
             castling_rights,
             en_passant_target,
+            halfmove_clock,
+            fullmove_number,
         };
 
         let rows = fields[0].split('/').collect::<Vec<&str>>();
@@ -302,6 +369,67 @@ impl Board {
         Ok(board)
     }
 
+    pub fn to_fen(self) -> String {
+        let mut rows = Vec::new();
+        for row in self.troops.chunks(8) {
+            let mut fen_row = String::new();
+            let mut empty = 0;
+            for troop in row {
+                match troop {
+                    Some(troop) => {
+                        if empty > 0 {
+                            fen_row.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen_row.push_str(&format!("{:?}", troop));
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen_row.push_str(&empty.to_string());
+            }
+            rows.push(fen_row);
+        }
+
+        let turn = match self.turn {
+            Color::White => "w",
+            Color::Black => "b",
+        };
+
+        let mut castling = String::new();
+        if self.castling_rights.white_king_side {
+            castling.push('K');
+        }
+        if self.castling_rights.white_queen_side {
+            castling.push('Q');
+        }
+        if self.castling_rights.black_king_side {
+            castling.push('k');
+        }
+        if self.castling_rights.black_queen_side {
+            castling.push('q');
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        let en_passant = self
+            .en_passant_target
+            .map(square_name)
+            .unwrap_or("-".into());
+
+        format!(
+            "{} {} {} {} {} {}",
+            rows.join("/"),
+            turn,
+            castling,
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
     pub fn moves(&self) -> Vec<Move> {
         let mut moves = Vec::new();
 
@@ -334,6 +462,67 @@ impl Board {
             .collect()
     }
 
+    /// Squares the piece on `start` could reach on an otherwise empty board, including pawn
+    /// captures and castling while the king still has the right to. The rest of the position will
+    /// have changed by the time a premove is played, so this is what premoves are checked
+    /// against.
+    pub fn premove_targets(&self, start: usize) -> Vec<usize> {
+        let Some(troop) = self.troops[start] else {
+            return Vec::new();
+        };
+        let mut alone = Board {
+            troops: [None; 64],
+            turn: troop.color,
+            castling_rights: CastlingRights {
+                white_king_side: false,
+                white_queen_side: false,
+                black_king_side: false,
+                black_queen_side: false,
+            },
+            en_passant_target: None,
+            ..*self
+        };
+        alone.troops[start] = Some(troop);
+
+        // Give pawns something to capture on both sides
+        if troop.piece == Piece::Pawn {
+            let direction = if troop.color == Color::White { 0 } else { 1 };
+            for diagonal_direction in [direction + 4, direction + 6] {
+                if NUM_SQUARES_TO_EDGE[start][diagonal_direction] != 0 {
+                    let diagonal = (start as i8 + DIRECTION_OFFSETS[diagonal_direction]) as usize;
+                    alone.troops[diagonal] = Some(Troop {
+                        color: !troop.color,
+                        piece: Piece::Pawn,
+                    });
+                }
+            }
+        }
+
+        let mut targets = alone
+            .moves()
+            .into_iter()
+            .filter(|m| m.start == start)
+            .map(|m| m.end)
+            .collect::<Vec<_>>();
+        targets.dedup();
+
+        if troop.piece == Piece::King {
+            let rights = &self.castling_rights;
+            let (king_side, queen_side, home) = match troop.color {
+                Color::White => (rights.white_king_side, rights.white_queen_side, 60),
+                Color::Black => (rights.black_king_side, rights.black_queen_side, 4),
+            };
+            // A FEN can give castling rights to a king that isn't on its home square
+            if king_side && start == home {
+                targets.push(start + 2);
+            }
+            if queen_side && start == home {
+                targets.push(start - 2);
+            }
+        }
+        targets
+    }
+
     fn generate_sliding_moves(&self, start: usize, piece: Piece) -> Vec<Move> {
         let mut moves = Vec::new();
 
@@ -350,7 +539,7 @@ impl Board {
                 let target = (start as i8 + (direction_offset * (n + 1))) as usize;
                 let troop = self.troops[target];
 
-                moves.push(Move { start, end: target });
+                moves.push(Move::new(start, target));
 
                 if troop.is_some() {
                     break;
@@ -365,42 +554,50 @@ impl Board {
         let mut moves = Vec::new();
 
         let direction = if self.turn == Color::White { 0 } else { 1 };
+        if NUM_SQUARES_TO_EDGE[start][direction] == 0 {
+            return moves;
+        }
 
-        if self.troops[(start as i8 + DIRECTION_OFFSETS[direction]) as usize].is_none() {
-            moves.push(Move {
-                start,
-                end: (start as i8 + DIRECTION_OFFSETS[direction]) as usize,
-            });
+        let forward = (start as i8 + DIRECTION_OFFSETS[direction]) as usize;
+        if self.troops[forward].is_none() {
+            moves.push(Move::new(start, forward));
 
-            if (start / 8 == 1 && self.turn == Color::Black)
-                || (start / 8 == 6 && self.turn == Color::White)
-                    && self.troops[(start as i8 + DIRECTION_OFFSETS[direction] * 2) as usize]
-                        .is_none()
+            let double_forward = (forward as i8 + DIRECTION_OFFSETS[direction]) as usize;
+            if ((start / 8 == 1 && self.turn == Color::Black)
+                || (start / 8 == 6 && self.turn == Color::White))
+                && self.troops[double_forward].is_none()
             {
-                moves.push(Move {
-                    start,
-                    end: (start as i8 + (DIRECTION_OFFSETS[direction] * 2)) as usize,
-                });
+                moves.push(Move::new(start, double_forward));
             }
         }
 
-        let diagonals = [
-            (start as i8 + DIRECTION_OFFSETS[direction + 4]) as usize,
-            (start as i8 + DIRECTION_OFFSETS[direction + 6]) as usize,
-        ];
-
-        for diagonal in diagonals {
-            if self.troops[diagonal].is_some()
-                || (self.troops[diagonal].is_none() && self.en_passant_target == Some(diagonal))
+        for diagonal_direction in [direction + 4, direction + 6] {
+            if NUM_SQUARES_TO_EDGE[start][diagonal_direction] == 0 {
+                continue;
+            }
+            let diagonal = (start as i8 + DIRECTION_OFFSETS[diagonal_direction]) as usize;
+            if self.troops[diagonal].is_some_and(|t| t.color != self.turn)
+                || self.en_passant_target == Some(diagonal)
             {
-                moves.push(Move {
-                    start,
-                    end: diagonal,
-                });
+                moves.push(Move::new(start, diagonal));
             }
         }
 
-        moves.into_iter().filter(|m| m.end < 64).collect()
+        let mut with_promotions = Vec::new();
+        for m in moves {
+            if m.end / 8 == 0 || m.end / 8 == 7 {
+                for piece in [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight] {
+                    with_promotions.push(Move {
+                        promotion: Some(piece),
+                        ..m
+                    });
+                }
+            } else {
+                with_promotions.push(m);
+            }
+        }
+
+        with_promotions
     }
 
     fn generate_knight_moves(&self, start: usize) -> Vec<Move> {
@@ -418,10 +615,10 @@ impl Board {
                         if (x_delta < 0 && x_delta.abs() <= NUM_SQUARES_TO_EDGE[start][2])
                             || (x_delta > 0 && x_delta.abs() <= NUM_SQUARES_TO_EDGE[start][3])
                         {
-                            moves.push(Move {
+                            moves.push(Move::new(
                                 start,
-                                end: (start as i8 + first_move + second_move) as usize,
-                            });
+                                (start as i8 + first_move + second_move) as usize,
+                            ));
                         }
                     }
                 }
@@ -435,10 +632,10 @@ impl Board {
                         if (y_delta < 0 && y_delta.abs() <= NUM_SQUARES_TO_EDGE[start][0])
                             || (y_delta > 0 && y_delta.abs() <= NUM_SQUARES_TO_EDGE[start][1])
                         {
-                            moves.push(Move {
+                            moves.push(Move::new(
                                 start,
-                                end: (start as i8 + first_move + second_move) as usize,
-                            });
+                                (start as i8 + first_move + second_move) as usize,
+                            ));
                         }
                     }
                 }
@@ -460,15 +657,502 @@ impl Board {
 
         for (i, cardinal) in DIRECTION_OFFSETS.into_iter().enumerate() {
             if NUM_SQUARES_TO_EDGE[start][i] != 0 {
-                moves.push(Move {
-                    start,
-                    end: (start as i8 + cardinal) as usize,
-                })
+                moves.push(Move::new(start, (start as i8 + cardinal) as usize))
             }
         }
 
+        let (king_side, queen_side, home) = match self.turn {
+            Color::White => (
+                self.castling_rights.white_king_side,
+                self.castling_rights.white_queen_side,
+                60,
+            ),
+            Color::Black => (
+                self.castling_rights.black_king_side,
+                self.castling_rights.black_queen_side,
+                4,
+            ),
+        };
+        if start != home || self.is_attacked(start, !self.turn) {
+            return moves;
+        }
+
+        let is_own_rook = |i: usize| {
+            self.troops[i].is_some_and(|t| t.piece == Piece::Rook && t.color == self.turn)
+        };
+        if king_side
+            && is_own_rook(start + 3)
+            && (start + 1..start + 3).all(|i| self.troops[i].is_none())
+            && (start + 1..start + 3).all(|i| !self.is_attacked(i, !self.turn))
+        {
+            moves.push(Move::new(start, start + 2));
+        }
+        if queen_side
+            && is_own_rook(start - 4)
+            && (start - 3..start).all(|i| self.troops[i].is_none())
+            && (start - 2..start).all(|i| !self.is_attacked(i, !self.turn))
+        {
+            moves.push(Move::new(start, start - 2));
+        }
+
         moves
     }
+
+    /// Pseudo-legal moves that don't leave the mover's king in check.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.moves()
+            .into_iter()
+            .filter(|m| {
+                let mut board = *self;
+                board.make_move(*m);
+                !board.is_in_check(self.turn)
+            })
+            .collect()
+    }
+
+    pub fn king_square(&self, color: Color) -> Option<usize> {
+        self.troops
+            .iter()
+            .position(|t| t.is_some_and(|t| t.piece == Piece::King && t.color == color))
+    }
+
+    pub fn is_in_check(&self, color: Color) -> bool {
+        self.king_square(color)
+            .is_some_and(|square| self.is_attacked(square, !color))
+    }
+
+    /// Whether any troop of color `by` attacks `square`.
+    pub fn is_attacked(&self, square: usize, by: Color) -> bool {
+        for (direction, offset) in DIRECTION_OFFSETS.iter().enumerate() {
+            let diagonal = direction >= 4;
+            for n in 0..NUM_SQUARES_TO_EDGE[square][direction] {
+                let target = (square as i8 + offset * (n + 1)) as usize;
+                let Some(troop) = self.troops[target] else {
+                    continue;
+                };
+                if troop.color == by {
+                    let attacks = match troop.piece {
+                        Piece::Queen => true,
+                        Piece::Rook => !diagonal,
+                        Piece::Bishop => diagonal,
+                        Piece::King => n == 0,
+                        // White pawns attack north, so they sit south of the square
+                        Piece::Pawn => {
+                            n == 0
+                                && match by {
+                                    Color::White => direction == 5 || direction == 7,
+                                    Color::Black => direction == 4 || direction == 6,
+                                }
+                        }
+                        Piece::Knight => false,
+                    };
+                    if attacks {
+                        return true;
+                    }
+                }
+                break;
+            }
+        }
+
+        knight_targets(square).any(|target| {
+            self.troops[target].is_some_and(|t| t.piece == Piece::Knight && t.color == by)
+        })
+    }
+
+    /// The square of the troop `m` would capture, if any. Differs from `m.end` for en passant.
+    pub fn capture_square(&self, m: &Move) -> Option<usize> {
+        if self.troops[m.end].is_some() {
+            return Some(m.end);
+        }
+        let troop = self.troops[m.start]?;
+        if troop.piece == Piece::Pawn && self.en_passant_target == Some(m.end) {
+            return Some(match troop.color {
+                Color::White => m.end + 8,
+                Color::Black => m.end - 8,
+            });
+        }
+        None
+    }
+
+    /// The rook's half of `m`, if `m` is a castling move.
+    pub fn castling_rook_move(&self, m: &Move) -> Option<Move> {
+        let troop = self.troops[m.start]?;
+        if troop.piece != Piece::King || (m.end as i8 - m.start as i8).abs() != 2 {
+            return None;
+        }
+        Some(if m.end > m.start {
+            Move::new(m.start + 3, m.start + 1)
+        } else {
+            Move::new(m.start - 4, m.start - 1)
+        })
+    }
+
+    /// Neither side could ever checkmate: only kings, a single minor piece, or bishops that are
+    /// all on the same color of square.
+    pub fn is_insufficient_material(&self) -> bool {
+        let mut minors = Vec::new();
+        for (i, troop) in self.troops.iter().enumerate() {
+            let Some(troop) = troop else {
+                continue;
+            };
+            match troop.piece {
+                Piece::King => {}
+                Piece::Bishop | Piece::Knight => minors.push((i, troop.piece)),
+                Piece::Queen | Piece::Rook | Piece::Pawn => return false,
+            }
+        }
+
+        minors.len() <= 1
+            || minors
+                .iter()
+                .all(|(i, piece)| *piece == Piece::Bishop && is_dark(*i) == is_dark(minors[0].0))
+    }
+
+    /// The result if the game is over by checkmate, stalemate, insufficient material or the
+    /// fifty-move rule. Repetitions need the game's history, so they aren't checked here.
+    pub fn outcome(&self) -> Option<(GameResult, Termination)> {
+        if self.legal_moves().is_empty() {
+            return Some(if self.is_in_check(self.turn) {
+                (GameResult::win_for(!self.turn), Termination::Checkmate)
+            } else {
+                (GameResult::Draw, Termination::Stalemate)
+            });
+        }
+        if self.is_insufficient_material() {
+            return Some((GameResult::Draw, Termination::InsufficientMaterial));
+        }
+        if self.halfmove_clock >= 100 {
+            return Some((GameResult::Draw, Termination::FiftyMoveRule));
+        }
+        None
+    }
+
+    /// The FEN without the move counters, which is what counts for repetitions. The en passant
+    /// square only counts if an en passant capture is actually legal.
+    pub fn position_key(&self) -> String {
+        let mut board = *self;
+        if let Some(target) = self.en_passant_target {
+            let can_capture = self.legal_moves().iter().any(|m| {
+                m.end == target && self.troops[m.start].is_some_and(|t| t.piece == Piece::Pawn)
+            });
+            if !can_capture {
+                board.en_passant_target = None;
+            }
+        }
+        board
+            .to_fen()
+            .split(' ')
+            .take(4)
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    /// `color`'s pieces that were on `start` but aren't any more, lowest value first. Pieces
+    /// beyond those on `start` are taken to have been promoted from pawns, which then don't count
+    /// as missing.
+    pub fn missing_pieces(&self, color: Color, start: &Board) -> Vec<Troop> {
+        let count = |board: &Board, piece| {
+            board
+                .troops
+                .iter()
+                .filter(|t| **t == Some(Troop { color, piece }))
+                .count()
+        };
+        let mut promoted = 0;
+        let mut missing = Vec::new();
+        for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+            let (before, now) = (count(start, piece), count(self, piece));
+            promoted += now.saturating_sub(before);
+            for _ in now..before {
+                missing.push(Troop { color, piece });
+            }
+        }
+        for _ in (count(self, Piece::Pawn) + promoted)..count(start, Piece::Pawn) {
+            missing.push(Troop {
+                color,
+                piece: Piece::Pawn,
+            });
+        }
+        missing.sort_by_key(|t| t.piece.points());
+        missing
+    }
+
+    /// Total points of `color`'s material, by `Piece::points`.
+    pub fn material(&self, color: Color) -> i32 {
+        self.troops
+            .iter()
+            .flatten()
+            .filter(|t| t.color == color)
+            .map(|t| t.piece.points())
+            .sum()
+    }
+
+    /// Whether `color` couldn't checkmate even with the opponent's help. Ignores the rare
+    /// positions where a lone minor piece can mate with the opponent's own pieces in the way.
+    pub fn has_insufficient_material(&self, color: Color) -> bool {
+        let mut minors = 0;
+        for troop in self.troops.iter().flatten().filter(|t| t.color == color) {
+            match troop.piece {
+                Piece::King => {}
+                Piece::Bishop | Piece::Knight => minors += 1,
+                Piece::Queen | Piece::Rook | Piece::Pawn => return false,
+            }
+        }
+        minors <= 1
+    }
+
+    /// Standard algebraic notation for `m`, which must be legal in this position.
+    pub fn san(&self, m: &Move) -> String {
+        let troop = self.troops[m.start].expect("No troop on the start square");
+        let mut san = String::new();
+
+        if self.castling_rook_move(m).is_some() {
+            san.push_str(if m.end > m.start { "O-O" } else { "O-O-O" });
+        } else {
+            let capture = self.capture_square(m).is_some();
+            if troop.piece == Piece::Pawn {
+                if capture {
+                    san.push(index_to_square(m.start).0);
+                }
+            } else {
+                san.push(troop.piece.letter());
+
+                // Other pieces of the same kind that could also reach the end square
+                let others = self
+                    .legal_moves()
+                    .into_iter()
+                    .filter(|other| {
+                        other.end == m.end
+                            && other.start != m.start
+                            && self.troops[other.start].is_some_and(|t| t.piece == troop.piece)
+                    })
+                    .map(|other| other.start)
+                    .collect::<Vec<usize>>();
+                if !others.is_empty() {
+                    let (file, rank) = index_to_square(m.start);
+                    if others.iter().all(|other| other % 8 != m.start % 8) {
+                        san.push(file);
+                    } else if others.iter().all(|other| other / 8 != m.start / 8) {
+                        san.push_str(&rank.to_string());
+                    } else {
+                        san.push_str(&square_name(m.start));
+                    }
+                }
+            }
+
+            if capture {
+                san.push('x');
+            }
+            san.push_str(&square_name(m.end));
+
+            if let Some(piece) = m.promotion {
+                san.push('=');
+                san.push(piece.letter());
+            }
+        }
+
+        let mut after = *self;
+        after.make_move(*m);
+        if after.is_in_check(after.turn) {
+            san.push(if after.legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+
+        san
+    }
+
+    /// Finds the legal move written as `text`, in SAN like "Nf3" or UCI like "g1f3". SAN is read
+    /// loosely, so check marks, "x" and "=" can be left out and castling can use zeros.
+    pub fn parse_move(&self, text: &str) -> Result<Move, String> {
+        let text = text.trim();
+        let legal_moves = self.legal_moves();
+        if let Ok(m) = Move::from_uci(text) {
+            if legal_moves.contains(&m) {
+                return Ok(m);
+            }
+        }
+
+        let loosen = |san: &str| -> String {
+            san.replace('0', "O")
+                .chars()
+                .filter(|c| !matches!(c, '+' | '#' | '!' | '?' | 'x' | '='))
+                .collect()
+        };
+        let wanted = loosen(text);
+        legal_moves
+            .into_iter()
+            .find(|m| loosen(&self.san(m)) == wanted)
+            .ok_or(format!("Illegal move {}", text))
+    }
+
+    /// Describes a move in words, like "Knight takes e5, check", for reading aloud.
+    pub fn describe_move(&self, m: &Move) -> String {
+        let troop = self.troops[m.start].expect("No troop on the start square");
+        let mut description = if self.castling_rook_move(m).is_some() {
+            if m.end > m.start {
+                "Castles kingside".to_string()
+            } else {
+                "Castles queenside".to_string()
+            }
+        } else {
+            let verb = match self.capture_square(m) {
+                Some(_) => "takes",
+                None => "to",
+            };
+            format!("{} {} {}", troop.piece, verb, square_name(m.end))
+        };
+        if let Some(piece) = m.promotion {
+            description += &format!(", promotes to {}", piece);
+        }
+
+        let mut after = *self;
+        after.make_move(*m);
+        if after.is_in_check(after.turn) {
+            description += if after.legal_moves().is_empty() {
+                ", checkmate"
+            } else {
+                ", check"
+            };
+        }
+        description
+    }
+
+    /// Like "e4, White Knight" or "e4, empty".
+    pub fn describe_square(&self, index: usize) -> String {
+        match self.troops[index] {
+            Some(troop) => format!("{}, {}", square_name(index), troop),
+            None => format!("{}, empty", square_name(index)),
+        }
+    }
+
+    /// Describes the whole position in words, like "White to move. White: King e1, Pawns e2
+    /// f2. Black: ...".
+    pub fn describe(&self) -> String {
+        let mut description = format!("{} to move", self.turn);
+        if self.is_in_check(self.turn) {
+            description += ", in check";
+        }
+        description.push('.');
+
+        for color in [Color::White, Color::Black] {
+            let mut groups = Vec::new();
+            for piece in [
+                Piece::King,
+                Piece::Queen,
+                Piece::Rook,
+                Piece::Bishop,
+                Piece::Knight,
+                Piece::Pawn,
+            ] {
+                let squares = (0..64)
+                    .filter(|i| self.troops[*i] == Some(Troop { color, piece }))
+                    .map(square_name)
+                    .collect::<Vec<_>>();
+                match squares.len() {
+                    0 => {}
+                    1 => groups.push(format!("{} {}", piece, squares[0])),
+                    _ => groups.push(format!("{}s {}", piece, squares.join(" "))),
+                }
+            }
+            description += &format!(" {}: {}.", color, groups.join(", "));
+        }
+        description
+    }
+
+    /// Plays `m` without checking that it's legal, returning the captured troop, if any.
+    pub fn make_move(&mut self, m: Move) -> Option<Troop> {
+        let troop = self.troops[m.start].expect("No troop on the start square");
+        let captured_square = self.capture_square(&m);
+        let rook_move = self.castling_rook_move(&m);
+
+        let captured = captured_square.and_then(|i| self.troops[i].take());
+        self.troops[m.start] = None;
+        self.troops[m.end] = Some(match m.promotion {
+            Some(piece) => Troop {
+                color: troop.color,
+                piece,
+            },
+            None => troop,
+        });
+        if let Some(rook_move) = rook_move {
+            self.troops[rook_move.end] = self.troops[rook_move.start].take();
+        }
+
+        self.en_passant_target =
+            if troop.piece == Piece::Pawn && (m.end as i8 - m.start as i8).abs() == 16 {
+                Some((m.start + m.end) / 2)
+            } else {
+                None
+            };
+
+        self.castling_rights.remove_for_square(m.start);
+        self.castling_rights.remove_for_square(m.end);
+
+        if captured.is_some() || troop.piece == Piece::Pawn {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if troop.color == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.turn = !self.turn;
+
+        captured
+    }
+}
+
+impl CastlingRights {
+    /// Moving from or capturing on a king's or rook's home square loses the matching rights.
+    fn remove_for_square(&mut self, square: usize) {
+        match square {
+            0 => self.black_queen_side = false,
+            4 => {
+                self.black_king_side = false;
+                self.black_queen_side = false;
+            }
+            7 => self.black_king_side = false,
+            56 => self.white_queen_side = false,
+            60 => {
+                self.white_king_side = false;
+                self.white_queen_side = false;
+            }
+            63 => self.white_king_side = false,
+            _ => {}
+        }
+    }
+}
+
+pub fn is_dark(index: usize) -> bool {
+    (index / 8) % 2 != (index % 8) % 2
+}
+
+fn knight_targets(square: usize) -> impl Iterator<Item = usize> {
+    let file = (square % 8) as i8;
+    let rank = (square / 8) as i8;
+    [
+        (1, 2),
+        (2, 1),
+        (2, -1),
+        (1, -2),
+        (-1, -2),
+        (-2, -1),
+        (-2, 1),
+        (-1, 2),
+    ]
+    .into_iter()
+    .filter_map(move |(file_delta, rank_delta)| {
+        let file = file + file_delta;
+        let rank = rank + rank_delta;
+        if (0..8).contains(&file) && (0..8).contains(&rank) {
+            Some((rank * 8 + file) as usize)
+        } else {
+            None
+        }
+    })
 }
 
 impl Display for Board {
@@ -504,8 +1188,295 @@ impl Debug for Board {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+impl GameResult {
+    pub fn win_for(color: Color) -> Self {
+        match color {
+            Color::White => GameResult::WhiteWins,
+            Color::Black => GameResult::BlackWins,
+        }
+    }
+}
+impl Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                GameResult::WhiteWins => "1-0",
+                GameResult::BlackWins => "0-1",
+                GameResult::Draw => "1/2-1/2",
+            }
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    InsufficientMaterial,
+    FiftyMoveRule,
+    ThreefoldRepetition,
+    Timeout,
+    /// The flag fell, but the opponent couldn't have won anyway.
+    TimeoutVsInsufficientMaterial,
+}
+impl Display for Termination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Termination::Checkmate => "Checkmate",
+                Termination::Stalemate => "Stalemate",
+                Termination::InsufficientMaterial => "Insufficient material",
+                Termination::FiftyMoveRule => "Fifty-move rule",
+                Termination::ThreefoldRepetition => "Threefold repetition",
+                Termination::Timeout => "Timeout",
+                Termination::TimeoutVsInsufficientMaterial => "Timeout vs insufficient material",
+            }
+        )
+    }
+}
+
+/// The result when `flagged` runs out of time.
+pub fn timeout_result(board: &Board, flagged: Color) -> (GameResult, Termination) {
+    if board.has_insufficient_material(!flagged) {
+        (GameResult::Draw, Termination::TimeoutVsInsufficientMaterial)
+    } else {
+        (GameResult::win_for(!flagged), Termination::Timeout)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Move {
     pub start: usize,
     pub end: usize,
+    pub promotion: Option<Piece>,
+}
+
+impl Move {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self {
+            start,
+            end,
+            promotion: None,
+        }
+    }
+
+    /// Parses a move like "e2e4" or "e7e8q". Doesn't check that it's legal.
+    pub fn from_uci(uci: &str) -> Result<Self, String> {
+        if !uci.is_ascii() || !(4..=5).contains(&uci.len()) {
+            return Err(format!("Invalid move {}", uci));
+        }
+
+        let promotion = match uci[4..].chars().next() {
+            Some(letter) => Some(
+                Piece::from_letter(letter)
+                    .filter(|p| !matches!(p, Piece::King | Piece::Pawn))
+                    .ok_or(format!("Invalid promotion {}", letter))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            start: parse_square(&uci[..2])?,
+            end: parse_square(&uci[2..4])?,
+            promotion,
+        })
+    }
+
+    pub fn to_uci(self) -> String {
+        let mut uci = format!("{}{}", square_name(self.start), square_name(self.end));
+        if let Some(piece) = self.promotion {
+            uci.push(piece.letter().to_ascii_lowercase());
+        }
+        uci
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(fen: &str) -> Board {
+        Board::from_fen(fen).unwrap()
+    }
+
+    fn square(name: &str) -> usize {
+        parse_square(name).unwrap()
+    }
+
+    #[test]
+    fn insufficient_material() {
+        for fen in [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4KN2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4KB2 w - - 0 1",
+            // Bishops on the same colour of square
+            "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+        ] {
+            assert!(board(fen).is_insufficient_material(), "{}", fen);
+        }
+        for fen in [
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1",
+            "4kb2/8/8/8/8/8/8/3BK3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/3NKN2 w - - 0 1",
+        ] {
+            assert!(!board(fen).is_insufficient_material(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn premoves_castle_only_from_the_home_square() {
+        let home = board("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1");
+        let targets = home.premove_targets(square("e1"));
+        assert!(targets.contains(&square("g1")));
+        assert!(targets.contains(&square("c1")));
+
+        // Rights the FEN gives to kings that have wandered off
+        let away = board("k7/8/8/8/8/8/8/7K b Kq - 0 1");
+        for start in [square("h1"), square("a8")] {
+            let targets = away.premove_targets(start);
+            assert!(targets.iter().all(|end| *end < 64), "{:?}", targets);
+            assert_eq!(targets.len(), 3);
+        }
+    }
+
+    #[test]
+    fn missing_pieces_count_promotions_as_pawns() {
+        let start = Board::starting();
+        let pawn = |color| Troop {
+            color,
+            piece: Piece::Pawn,
+        };
+
+        let knight_taken = board("r1bqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(
+            knight_taken.missing_pieces(Color::Black, &start),
+            [Troop {
+                color: Color::Black,
+                piece: Piece::Knight,
+            }]
+        );
+        assert!(knight_taken.missing_pieces(Color::White, &start).is_empty());
+
+        // The h-pawn became the second queen
+        let promoted = board("rnbqkbnr/pppppppp/8/8/3Q4/8/PPPPPPP1/RNBQKBNR w KQkq - 0 1");
+        assert!(promoted.missing_pieces(Color::White, &start).is_empty());
+        let pawn_taken = board("rnbqkbnr/pppppppp/8/8/3Q4/8/PPPPPP2/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(
+            pawn_taken.missing_pieces(Color::White, &start),
+            [pawn(Color::White)]
+        );
+        let rook_taken = board("rnbqkbnr/pppppppp/8/8/3Q4/8/PPPPPPP1/RNBQKBN1 w Qkq - 0 1");
+        assert_eq!(
+            rook_taken.missing_pieces(Color::White, &start),
+            [Troop {
+                color: Color::White,
+                piece: Piece::Rook,
+            }]
+        );
+    }
+
+    #[test]
+    fn missing_pieces_count_from_the_loaded_position() {
+        let endgame = board("4k3/8/8/8/8/8/4P3/R3K3 w - - 0 1");
+        assert!(endgame.missing_pieces(Color::White, &endgame).is_empty());
+        assert!(endgame.missing_pieces(Color::Black, &endgame).is_empty());
+
+        let rook_taken = board("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+        assert_eq!(
+            rook_taken.missing_pieces(Color::White, &endgame),
+            [Troop {
+                color: Color::White,
+                piece: Piece::Rook,
+            }]
+        );
+    }
+
+    #[test]
+    fn timeout_against_insufficient_material_is_a_draw() {
+        let lone_knight = board("4k3/8/8/8/8/8/8/Q3KN2 w - - 0 1");
+        assert_eq!(
+            timeout_result(&lone_knight, Color::White),
+            (GameResult::Draw, Termination::TimeoutVsInsufficientMaterial)
+        );
+        assert_eq!(
+            timeout_result(&lone_knight, Color::Black),
+            (GameResult::WhiteWins, Termination::Timeout)
+        );
+    }
+
+    fn uci(board: &Board, text: &str) -> Result<String, String> {
+        board.parse_move(text).map(|m| m.to_uci())
+    }
+
+    #[test]
+    fn parses_san_and_uci() {
+        let starting = Board::starting();
+        assert_eq!(uci(&starting, "Nf3"), Ok("g1f3".into()));
+        assert_eq!(uci(&starting, "g1f3"), Ok("g1f3".into()));
+        assert_eq!(uci(&starting, " e4 "), Ok("e2e4".into()));
+        assert!(uci(&starting, "e5").is_err());
+        assert!(uci(&starting, "e2e5").is_err());
+        // Piece letters are case sensitive, since "b" is also a file
+        assert!(uci(&starting, "nf3").is_err());
+    }
+
+    #[test]
+    fn parses_san_loosely() {
+        let position = board("r3k2r/1P6/8/3p4/4P3/8/8/R3K2R w KQkq - 0 1");
+        assert_eq!(uci(&position, "exd5"), Ok("e4d5".into()));
+        assert_eq!(uci(&position, "ed5"), Ok("e4d5".into()));
+        assert_eq!(uci(&position, "0-0"), Ok("e1g1".into()));
+        assert_eq!(uci(&position, "O-O-O"), Ok("e1c1".into()));
+        assert_eq!(uci(&position, "bxa8=Q+"), Ok("b7a8q".into()));
+        assert_eq!(uci(&position, "ba8Q"), Ok("b7a8q".into()));
+        assert_eq!(uci(&position, "b8=N"), Ok("b7b8n".into()));
+        assert_eq!(uci(&position, "b7b8r"), Ok("b7b8r".into()));
+    }
+
+    #[test]
+    fn tells_apart_pieces_that_reach_the_same_square() {
+        let position = board("4k3/8/8/8/8/8/8/2N1K1N1 w - - 0 1");
+        assert!(uci(&position, "Ne2").is_err());
+        assert_eq!(uci(&position, "Nce2"), Ok("c1e2".into()));
+        assert_eq!(uci(&position, "Nge2"), Ok("g1e2".into()));
+        assert_eq!(uci(&position, "Nb3"), Ok("c1b3".into()));
+    }
+
+    #[test]
+    fn describes_moves_in_words() {
+        let position = board("r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 0 1");
+        let describe = |text| position.describe_move(&position.parse_move(text).unwrap());
+        assert_eq!(describe("Qxf7#"), "Queen takes f7, checkmate");
+        assert_eq!(describe("Bxf7+"), "Bishop takes f7, check");
+        assert_eq!(describe("Nc3"), "Knight to c3");
+
+        let position = board("4k3/1P6/8/8/8/8/8/R3K2R w KQ - 0 1");
+        let describe = |text| position.describe_move(&position.parse_move(text).unwrap());
+        assert_eq!(describe("O-O"), "Castles kingside");
+        assert_eq!(describe("b8=N"), "Pawn to b8, promotes to Knight");
+    }
+
+    #[test]
+    fn describes_squares_and_positions() {
+        let starting = Board::starting();
+        assert_eq!(starting.describe_square(square("a8")), "a8, Black Rook");
+        assert_eq!(starting.describe_square(square("e4")), "e4, empty");
+
+        let position = board("4k3/8/8/8/8/8/PP6/R3K3 b Q - 0 1");
+        assert_eq!(
+            position.describe(),
+            "Black to move. White: King e1, Rook a1, Pawns a2 b2. Black: King e8."
+        );
+    }
 }